@@ -0,0 +1,110 @@
+//! Compares the size and load time of the JSON and binary encodings of a `ChangeSet`.
+//!
+//! Run with `cargo bench --bench changeset`.
+
+use std::{
+    hint::black_box,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bdk_wallet::{
+    bitcoin::{
+        absolute, hashes::Hash, transaction, Amount, BlockHash, OutPoint, ScriptBuf, Sequence, Transaction, TxIn,
+        TxOut, Txid, Witness,
+    },
+    chain::{BlockId, ConfirmationBlockTime},
+    ChangeSet as BdkChangeSet, KeychainKind, Wallet as BdkWallet,
+};
+use bitcoindevkit::types::ChangeSet;
+
+const EXTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p";
+const INTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/1/*)#dd6w3a4e";
+
+const ITERATIONS: u32 = 10;
+
+/// A changeset of a wallet that received `tx_count` confirmed transactions.
+fn wallet_changeset(tx_count: u32) -> BdkChangeSet {
+    let mut wallet = BdkWallet::create(EXTERNAL_DESC, INTERNAL_DESC)
+        .network(bdk_wallet::bitcoin::Network::Testnet)
+        .create_wallet_no_persist()
+        .expect("wallet");
+    let mut changeset = wallet.take_staged().expect("changeset");
+
+    for i in 0..tx_count {
+        let script_pubkey: ScriptBuf = wallet.reveal_next_address(KeychainKind::External).script_pubkey();
+        let tx = Transaction {
+            version: transaction::Version::TWO,
+            lock_time: absolute::LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(Txid::hash(&i.to_le_bytes()), 0),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+                witness: Witness::from_slice(&[vec![0x30; 72], vec![0x02; 33]]),
+            }],
+            output: vec![
+                TxOut {
+                    value: Amount::from_sat(10_000 + u64::from(i)),
+                    script_pubkey,
+                },
+                TxOut {
+                    value: Amount::from_sat(50_000),
+                    script_pubkey: ScriptBuf::new_op_return([0x6a; 20]),
+                },
+            ],
+        };
+        let txid = tx.compute_txid();
+        let height = 100_000 + i;
+        let anchor = ConfirmationBlockTime {
+            block_id: BlockId {
+                height,
+                hash: BlockHash::hash(&height.to_le_bytes()),
+            },
+            confirmation_time: 1_700_000_000 + u64::from(i) * 600,
+        };
+
+        changeset.tx_graph.txs.insert(Arc::new(tx));
+        changeset.tx_graph.anchors.insert((anchor, txid));
+        changeset.tx_graph.last_seen.insert(txid, anchor.confirmation_time);
+        changeset.local_chain.blocks.insert(height, Some(anchor.block_id.hash));
+    }
+
+    if let Some(staged) = wallet.take_staged() {
+        bdk_wallet::chain::Merge::merge(&mut changeset, staged);
+    }
+    changeset
+}
+
+fn time<T>(f: impl Fn() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>12} {:>12} {:>12} {:>12}",
+        "txs", "json size", "bytes size", "json load", "bytes load"
+    );
+
+    for tx_count in [100, 1_000, 5_000] {
+        let changeset = ChangeSet::from(wallet_changeset(tx_count));
+        let json = changeset.to_json();
+        let bytes = changeset.to_bytes();
+        assert!(ChangeSet::from_bytes(&bytes).expect("bytes") == changeset);
+
+        let json_load = time(|| ChangeSet::from_json(&json).expect("json"));
+        let bytes_load = time(|| ChangeSet::from_bytes(&bytes).expect("bytes"));
+
+        println!(
+            "{:>8} {:>12} {:>12} {:>12.2?} {:>12.2?}",
+            tx_count,
+            json.len(),
+            bytes.len(),
+            json_load,
+            bytes_load
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use bdk_wallet::{
+    bitcoin::secp256k1::rand::{thread_rng, RngCore},
+    chain::Indexer,
+    ChangeSet as BdkChangeSet, KeychainKind, Wallet as BdkWallet,
+};
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    KeyInit, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ChangeSet, ErrorCode},
+    utils::Bytes,
+};
+
+/// Version of the encrypted backup format, the first byte of a backup.
+const BACKUP_VERSION: u8 = 1;
+
+// The header starts with the version and the Argon2 memory, iterations and parallelism costs,
+// followed by the salt and the nonce.
+const SALT_OFFSET: usize = 1 + 3 * 4;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = SALT_OFFSET + SALT_LEN + NONCE_LEN;
+// Upper bounds on the Argon2 costs read from a backup: memory in KiB, iterations and parallelism.
+const MAX_M_COST: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 64;
+const MAX_P_COST: u32 = 16;
+
+/// Content of an encrypted backup.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Backup {
+    /// Descriptor of the external keychain, including its private keys if any.
+    pub external_descriptor: String,
+    /// Descriptor of the internal keychain, including its private keys if any.
+    pub internal_descriptor: String,
+    /// Binary encoding of the wallet's [`ChangeSet`].
+    pub changeset: Bytes,
+}
+
+impl Backup {
+    /// Back up the descriptors and the whole state of `wallet`.
+    pub(crate) fn from_wallet(wallet: &BdkWallet) -> Backup {
+        let descriptor = |keychain| {
+            let key_map = wallet.get_signers(keychain).as_key_map(wallet.secp_ctx());
+            wallet.public_descriptor(keychain).to_string_with_secret(&key_map)
+        };
+
+        let changeset = BdkChangeSet {
+            descriptor: Some(wallet.public_descriptor(KeychainKind::External).clone()),
+            change_descriptor: Some(wallet.public_descriptor(KeychainKind::Internal).clone()),
+            network: Some(wallet.network()),
+            local_chain: wallet.local_chain().initial_changeset(),
+            tx_graph: wallet.tx_graph().initial_changeset(),
+            indexer: wallet.spk_index().initial_changeset(),
+        };
+
+        Backup {
+            external_descriptor: descriptor(KeychainKind::External),
+            internal_descriptor: descriptor(KeychainKind::Internal),
+            changeset: Bytes(ChangeSet::from(changeset).to_bytes()),
+        }
+    }
+
+    /// Encrypt the backup with a key derived from `password` with Argon2id, using XChaCha20-Poly1305.
+    ///
+    /// The blob is a header holding the format version, the Argon2 parameters, the salt and the nonce,
+    /// followed by the ciphertext. The header is authenticated along with the ciphertext.
+    pub(crate) fn encrypt(&self, password: &str) -> JsResult<Vec<u8>> {
+        let params = Params::default();
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut salt);
+        thread_rng().fill_bytes(&mut nonce);
+
+        let mut blob = Vec::with_capacity(HEADER_LEN);
+        blob.push(BACKUP_VERSION);
+        blob.extend(params.m_cost().to_le_bytes());
+        blob.extend(params.t_cost().to_le_bytes());
+        blob.extend(params.p_cost().to_le_bytes());
+        blob.extend(salt);
+        blob.extend(nonce);
+
+        let mut plaintext = Zeroizing::new(Vec::new());
+        ciborium::into_writer(self, &mut *plaintext).expect("Serialization should not fail");
+
+        let cipher = cipher(password, params, &salt)?;
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &blob,
+                },
+            )
+            .map_err(|_| BdkError::new(ErrorCode::Unknown, "Failed to encrypt the backup"))?;
+
+        blob.extend(ciphertext);
+        Ok(blob)
+    }
+
+    /// Decrypt a backup produced by [`Backup::encrypt`].
+    pub(crate) fn decrypt(blob: &[u8], password: &str) -> JsResult<Backup> {
+        if blob.len() < HEADER_LEN {
+            return Err(BdkError::new(ErrorCode::InvalidData, "Invalid backup: too short"));
+        }
+        let (header, ciphertext) = blob.split_at(HEADER_LEN);
+        if header[0] != BACKUP_VERSION {
+            return Err(BdkError::new(
+                ErrorCode::UnsupportedVersion,
+                format!("Unsupported backup version: {}", header[0]),
+            )
+            .with("version", header[0]));
+        }
+
+        let cost = |i: usize| u32::from_le_bytes(header[1 + 4 * i..5 + 4 * i].try_into().expect("4 bytes"));
+        for (i, name, max) in [
+            (0, "memory", MAX_M_COST),
+            (1, "iterations", MAX_T_COST),
+            (2, "parallelism", MAX_P_COST),
+        ] {
+            if cost(i) > max {
+                return Err(BdkError::new(
+                    ErrorCode::InvalidData,
+                    format!("Invalid backup parameters: {name} cost too high"),
+                ));
+            }
+        }
+        let params = Params::new(cost(0), cost(1), cost(2), None)
+            .map_err(|e| BdkError::new(ErrorCode::InvalidData, format!("Invalid backup parameters: {e}")))?;
+        let salt = &header[SALT_OFFSET..SALT_OFFSET + SALT_LEN];
+        let nonce = &header[SALT_OFFSET + SALT_LEN..];
+
+        let plaintext = cipher(password, params, salt)?
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                BdkError::new(
+                    ErrorCode::DecryptionFailed,
+                    "Failed to decrypt the backup, check the password",
+                )
+            })
+            .map(Zeroizing::new)?;
+
+        Ok(ciborium::from_reader(plaintext.as_slice())?)
+    }
+}
+
+/// Derive the encryption key from `password`.
+fn cipher(password: &str, params: Params, salt: &[u8]) -> JsResult<XChaCha20Poly1305> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut *key)
+        .map_err(|e| BdkError::new(ErrorCode::InvalidData, format!("Failed to derive the backup key: {e}")))?;
+    Ok(XChaCha20Poly1305::new((&*key).into()))
+}
@@ -0,0 +1,311 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    str::FromStr,
+};
+
+use bdk_wallet::{
+    bitcoin::{secp256k1::rand::RngCore, Amount, FeeRate, OutPoint, Script, TxIn, Weight},
+    coin_selection::{
+        decide_change, CoinSelectionAlgorithm, CoinSelectionResult, Excess, InsufficientFunds,
+        LargestFirstCoinSelection, OldestFirstCoinSelection, SingleRandomDraw,
+    },
+    Utxo, WeightedUtxo,
+};
+use js_sys::{Array, Function, Object};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+use crate::types::{Amount as JsAmount, BdkError, ErrorCode, FeeRate as JsFeeRate, LocalOutput};
+
+/// Coin selection algorithms that can be used by a [`TxBuilder`](super::TxBuilder).
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CoinSelection {
+    /// Branch and bound coin selection, looking for a changeless solution (default).
+    ///
+    /// Falls back to [`CoinSelection::SingleRandomDraw`] when no exact match is found, or to the
+    /// lowest-waste simple algorithm if a long-term fee rate is given.
+    BranchAndBound = "branch_and_bound",
+    /// Picks the largest UTXOs first until the required amount is reached.
+    LargestFirst = "largest_first",
+    /// Picks the oldest UTXOs first until the required amount is reached.
+    OldestFirst = "oldest_first",
+    /// Picks random UTXOs until the required amount is reached.
+    SingleRandomDraw = "single_random_draw",
+}
+
+/// Runs the simple coin selection algorithms and keeps the selection with the lowest waste.
+///
+/// The waste metric is the one used by Bitcoin Core: the cost of spending the inputs now rather
+/// than at the `long_term_fee_rate`, plus either the cost of creating and later spending the
+/// change output or the excess given to miners when there is no change.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LowestWasteCoinSelection {
+    long_term_fee_rate: FeeRate,
+}
+
+impl LowestWasteCoinSelection {
+    pub(crate) fn new(long_term_fee_rate: FeeRate) -> Self {
+        LowestWasteCoinSelection { long_term_fee_rate }
+    }
+
+    fn waste(&self, result: &CoinSelectionResult, weights: &HashMap<OutPoint, Weight>, fee_rate: FeeRate) -> i64 {
+        let input_weights = result
+            .selected
+            .iter()
+            .map(|utxo| input_weight(weights.get(&utxo.outpoint()).copied().unwrap_or(Weight::ZERO)));
+
+        let mut waste: i64 = input_weights
+            .clone()
+            .map(|weight| sats(weight * fee_rate) - sats(weight * self.long_term_fee_rate))
+            .sum();
+
+        waste += match result.excess {
+            Excess::Change { fee, .. } => {
+                // The change output is spent later with the same descriptor as the selected inputs.
+                let spend_weight = input_weights.max().unwrap_or(input_weight(Weight::ZERO));
+                sats(fee) + sats(spend_weight * self.long_term_fee_rate)
+            }
+            Excess::NoChange { remaining_amount, .. } => sats(remaining_amount),
+        };
+
+        waste
+    }
+}
+
+impl CoinSelectionAlgorithm for LowestWasteCoinSelection {
+    fn coin_select<R: RngCore>(
+        &self,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: Amount,
+        drain_script: &Script,
+        rand: &mut R,
+    ) -> Result<CoinSelectionResult, InsufficientFunds> {
+        let weights: HashMap<OutPoint, Weight> = required_utxos
+            .iter()
+            .chain(optional_utxos.iter())
+            .map(|utxo| (utxo.utxo.outpoint(), utxo.satisfaction_weight))
+            .collect();
+
+        let candidates = [
+            LargestFirstCoinSelection.coin_select(
+                required_utxos.clone(),
+                optional_utxos.clone(),
+                fee_rate,
+                target_amount,
+                drain_script,
+                rand,
+            ),
+            OldestFirstCoinSelection.coin_select(
+                required_utxos.clone(),
+                optional_utxos.clone(),
+                fee_rate,
+                target_amount,
+                drain_script,
+                rand,
+            ),
+            SingleRandomDraw.coin_select(
+                required_utxos,
+                optional_utxos,
+                fee_rate,
+                target_amount,
+                drain_script,
+                rand,
+            ),
+        ];
+
+        let mut best: Option<(i64, CoinSelectionResult)> = None;
+        let mut error = None;
+        for candidate in candidates {
+            match candidate {
+                Ok(result) => {
+                    let waste = self.waste(&result, &weights, fee_rate);
+                    if best.as_ref().map_or(true, |(best_waste, _)| waste < *best_waste) {
+                        best = Some((waste, result));
+                    }
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+
+        match best {
+            Some((_, result)) => Ok(result),
+            None => Err(error.expect("at least one coin selection result")),
+        }
+    }
+}
+
+/// Coin selection delegated to a JavaScript function.
+///
+/// The function is called with the candidate [`LocalOutput`]s, the target amount and the fee rate, and
+/// must return the outpoints to spend, as `OutPoint`s or `"txid:vout"` strings. The returned outpoints
+/// are checked against the candidates, then the fee and change are computed the same way as the
+/// built-in algorithms.
+#[derive(Debug)]
+pub(crate) struct JsCoinSelection {
+    callback: Function,
+    // `CoinSelectionAlgorithm` can only fail with `InsufficientFunds`, so other errors are kept here
+    // for the `TxBuilder` to report them.
+    error: Rc<RefCell<Option<BdkError>>>,
+}
+
+impl JsCoinSelection {
+    pub(crate) fn new(callback: Function) -> Self {
+        JsCoinSelection {
+            callback,
+            error: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Shared handle to the error raised by the callback, if any.
+    pub(crate) fn error(&self) -> Rc<RefCell<Option<BdkError>>> {
+        self.error.clone()
+    }
+
+    fn select(
+        &self,
+        candidates: &[WeightedUtxo],
+        fee_rate: FeeRate,
+        target_amount: Amount,
+    ) -> Result<Vec<OutPoint>, BdkError> {
+        let local_outputs: Array = candidates
+            .iter()
+            .filter_map(|candidate| match &candidate.utxo {
+                Utxo::Local(output) => Some(JsValue::from(LocalOutput::from(output.clone()))),
+                Utxo::Foreign { .. } => None,
+            })
+            .collect();
+
+        let selection = self
+            .callback
+            .call3(
+                &JsValue::NULL,
+                &local_outputs,
+                &JsAmount::from(target_amount).into(),
+                &JsFeeRate::from(fee_rate).into(),
+            )
+            .map_err(|e| {
+                BdkError::new(
+                    ErrorCode::CoinSelection,
+                    format!("Coin selection callback failed: {}", js_error_message(&e)),
+                )
+            })?;
+
+        if !Array::is_array(&selection) {
+            return Err(BdkError::new(
+                ErrorCode::CoinSelection,
+                "Coin selection callback must return an array of outpoints",
+            ));
+        }
+
+        let candidates: HashSet<OutPoint> = candidates.iter().map(|candidate| candidate.utxo.outpoint()).collect();
+        let mut selected = Vec::new();
+        for value in Array::from(&selection).iter() {
+            let outpoint_str = match value.as_string() {
+                Some(outpoint_str) => outpoint_str,
+                None if value.is_object() => String::from(Object::from(value).to_string()),
+                None => {
+                    return Err(BdkError::new(
+                        ErrorCode::CoinSelection,
+                        format!("Coin selection callback must return outpoints, got {value:?}"),
+                    ))
+                }
+            };
+            let outpoint = OutPoint::from_str(&outpoint_str).map_err(|e| {
+                BdkError::new(
+                    ErrorCode::CoinSelection,
+                    format!("Invalid outpoint {outpoint_str}: {e}"),
+                )
+            })?;
+
+            if !candidates.contains(&outpoint) {
+                return Err(BdkError::new(
+                    ErrorCode::CoinSelection,
+                    format!("Outpoint {outpoint} returned by the coin selection callback is not a candidate"),
+                ));
+            }
+            if selected.contains(&outpoint) {
+                return Err(BdkError::new(
+                    ErrorCode::CoinSelection,
+                    format!("Outpoint {outpoint} returned more than once by the coin selection callback"),
+                ));
+            }
+            selected.push(outpoint);
+        }
+
+        Ok(selected)
+    }
+}
+
+impl CoinSelectionAlgorithm for JsCoinSelection {
+    fn coin_select<R: RngCore>(
+        &self,
+        required_utxos: Vec<WeightedUtxo>,
+        optional_utxos: Vec<WeightedUtxo>,
+        fee_rate: FeeRate,
+        target_amount: Amount,
+        drain_script: &Script,
+        _: &mut R,
+    ) -> Result<CoinSelectionResult, InsufficientFunds> {
+        let chosen = match self.select(&optional_utxos, fee_rate, target_amount) {
+            Ok(chosen) => chosen,
+            Err(e) => {
+                self.error.replace(Some(e));
+                return Err(InsufficientFunds {
+                    needed: target_amount,
+                    available: Amount::ZERO,
+                });
+            }
+        };
+
+        let mut optional_utxos: HashMap<OutPoint, WeightedUtxo> = optional_utxos
+            .into_iter()
+            .map(|utxo| (utxo.utxo.outpoint(), utxo))
+            .collect();
+        let selected: Vec<WeightedUtxo> = required_utxos
+            .into_iter()
+            .chain(chosen.iter().filter_map(|outpoint| optional_utxos.remove(outpoint)))
+            .collect();
+
+        let selected_amount: Amount = selected.iter().map(|utxo| utxo.utxo.txout().value).sum();
+        let fee_amount: Amount = selected
+            .iter()
+            .map(|utxo| fee_rate * input_weight(utxo.satisfaction_weight))
+            .sum();
+
+        let amount_needed_with_fees = target_amount + fee_amount;
+        if selected_amount < amount_needed_with_fees {
+            return Err(InsufficientFunds {
+                needed: amount_needed_with_fees,
+                available: selected_amount,
+            });
+        }
+
+        let excess = decide_change(selected_amount - amount_needed_with_fees, fee_rate, drain_script);
+
+        Ok(CoinSelectionResult {
+            selected: selected.into_iter().map(|utxo| utxo.utxo).collect(),
+            fee_amount,
+            excess,
+        })
+    }
+}
+
+/// Weight of an input spending a UTXO with the given satisfaction weight.
+fn input_weight(satisfaction_weight: Weight) -> Weight {
+    TxIn::default().segwit_weight() + satisfaction_weight
+}
+
+fn sats(amount: Amount) -> i64 {
+    amount.to_sat() as i64
+}
+
+fn js_error_message(value: &JsValue) -> String {
+    match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => error.message().into(),
+        None => value.as_string().unwrap_or_else(|| format!("{value:?}")),
+    }
+}
@@ -1,12 +1,24 @@
 use anyhow::{anyhow, Error};
+use std::str::FromStr;
+
 use bdk_wallet::{
     bitcoin::{
         bip32::{ChainCode, Fingerprint, Xpriv, Xpub},
+        consensus::encode::VarInt,
+        hashes::{hash160, ripemd160, sha256},
         hex::FromHex,
-        secp256k1::{PublicKey, SecretKey},
-        AddressType, Network,
+        secp256k1::{All, PublicKey, Secp256k1, SecretKey},
+        taproot::{TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_NODE_SIZE},
+        AddressType, Network, Weight,
+    },
+    descriptor::{Descriptor, DescriptorPublicKey},
+    keys::{DerivableKey, DescriptorSecretKey, ExtendedKey, KeyMap},
+    miniscript::{
+        descriptor::{DerivPaths, DescriptorMultiXKey},
+        hash256,
+        policy::{concrete::DescriptorCtx, Concrete},
+        Segwitv0, TranslateErr, TranslatePk, Translator,
     },
-    keys::{DerivableKey, ExtendedKey},
     template::{
         Bip44, Bip44Public, Bip49, Bip49Public, Bip84, Bip84Public, Bip86, Bip86Public, DescriptorTemplate,
         DescriptorTemplateOut,
@@ -105,6 +117,159 @@ pub fn seed_to_xpriv(seed: &[u8], network: Network) -> Result<Xpriv, Error> {
     Ok(xprv)
 }
 
+/// Compile a Miniscript concrete `policy` into a `wsh` descriptor, or a `tr` descriptor if `taproot`.
+///
+/// The internal key of a `tr` descriptor is the most probable key of the policy, or the unspendable
+/// key of BIP341 if no key can be extracted.
+pub fn compile_policy(policy: &str, taproot: bool) -> Result<Descriptor<DescriptorPublicKey>, Error> {
+    let policy = Concrete::<DescriptorPublicKey>::from_str(policy)?;
+
+    let context = match taproot {
+        true => DescriptorCtx::Tr(Some(unspendable_key())),
+        false => DescriptorCtx::Wsh,
+    };
+    let descriptor = policy.compile_to_descriptor::<Segwitv0>(context)?;
+    descriptor.sanity_check()?;
+
+    Ok(descriptor)
+}
+
+/// Worst-case weight of the witness satisfying each taproot spending path of `descriptor`.
+///
+/// A `tr` descriptor has a path for its internal key, unless it is unspendable, then one for each
+/// leaf of its script tree. For other descriptors, the branches of the script are not told apart: the
+/// single weight returned is the worst case over all of them, as given by `max_weight_to_satisfy`.
+pub fn satisfaction_weights(descriptor: &Descriptor<DescriptorPublicKey>) -> Result<Vec<Weight>, Error> {
+    let tr = match descriptor {
+        Descriptor::Tr(tr) => tr,
+        descriptor => return Ok(vec![descriptor.max_weight_to_satisfy()?]),
+    };
+
+    let varint_len = |n: usize| VarInt(n as u64).size();
+    let mut weights = Vec::new();
+    if *tr.internal_key() != unspendable_key() {
+        // A single element: the signature with its sighash byte.
+        weights.push(Weight::from_wu((varint_len(65) + 65) as u64));
+    }
+    for (depth, miniscript) in tr.iter_scripts() {
+        let script_size = miniscript.script_size();
+        let control_block_size = TAPROOT_CONTROL_BASE_SIZE + depth as usize * TAPROOT_CONTROL_NODE_SIZE;
+        // The satisfaction elements, then the script and the control block.
+        let elements = miniscript.max_satisfaction_witness_elements()? + 1;
+        let weight = varint_len(elements) - varint_len(0)
+            + miniscript.max_satisfaction_size()?
+            + varint_len(script_size)
+            + script_size
+            + varint_len(control_block_size)
+            + control_block_size;
+        weights.push(Weight::from_wu(weight as u64));
+    }
+
+    Ok(weights)
+}
+
+/// A descriptor with the private keys it was given.
+pub type DescriptorKeys = (Descriptor<DescriptorPublicKey>, KeyMap);
+
+/// Parse a descriptor along with its private keys.
+///
+/// Unlike `Descriptor::parse_descriptor`, BIP389 multipath extended private keys, such as
+/// `xprv/84'/0'/0'/<0;1>/*`, are supported as long as their paths share the same hardened steps.
+pub fn parse_descriptor(descriptor: &str) -> Result<DescriptorKeys, Error> {
+    let descriptor = Descriptor::<String>::from_str(descriptor)?;
+
+    let mut parser = KeyParser {
+        secp: Secp256k1::new(),
+        key_map: KeyMap::new(),
+    };
+    let descriptor = match descriptor.translate_pk(&mut parser) {
+        Ok(descriptor) => descriptor,
+        Err(TranslateErr::TranslatorErr(e)) => return Err(e),
+        Err(TranslateErr::OuterError(e)) => return Err(e.into()),
+    };
+
+    Ok((descriptor, parser.key_map))
+}
+
+/// Parses the keys of a descriptor, recording the private keys by their public key.
+struct KeyParser {
+    secp: Secp256k1<All>,
+    key_map: KeyMap,
+}
+
+impl KeyParser {
+    /// The public key of a multipath extended private key, derived through the hardened steps of its
+    /// paths like for a single path key.
+    fn multipath_public_key(&self, secret: &DescriptorSecretKey) -> Result<DescriptorPublicKey, Error> {
+        let mut public = None;
+        let mut paths = Vec::new();
+        for key in secret.clone().into_single_keys() {
+            let xpub = match key.to_public(&self.secp)? {
+                DescriptorPublicKey::XPub(xpub) => xpub,
+                _ => unreachable!("the single keys of an extended key are extended keys"),
+            };
+            paths.push(xpub.derivation_path.clone());
+
+            let xkey = (xpub.origin, xpub.xkey, xpub.wildcard);
+            match &public {
+                None => public = Some(xkey),
+                Some(public) if *public == xkey => {}
+                Some(_) => {
+                    return Err(anyhow!(
+                        "The paths of a multipath private key must have the same hardened derivation steps"
+                    ))
+                }
+            }
+        }
+
+        let (origin, xkey, wildcard) = public.expect("a multipath key has several paths");
+        Ok(DescriptorPublicKey::MultiXPub(DescriptorMultiXKey {
+            origin,
+            xkey,
+            derivation_paths: DerivPaths::new(paths).expect("a multipath key has several paths"),
+            wildcard,
+        }))
+    }
+}
+
+impl Translator<String, DescriptorPublicKey, Error> for KeyParser {
+    fn pk(&mut self, key: &String) -> Result<DescriptorPublicKey, Error> {
+        let secret = match DescriptorSecretKey::from_str(key) {
+            Ok(secret) => secret,
+            Err(_) => return Ok(DescriptorPublicKey::from_str(key)?),
+        };
+        let public = match secret.is_multipath() {
+            true => self.multipath_public_key(&secret)?,
+            false => secret.to_public(&self.secp)?,
+        };
+
+        self.key_map.insert(public.clone(), secret);
+        Ok(public)
+    }
+
+    fn sha256(&mut self, sha256: &String) -> Result<sha256::Hash, Error> {
+        Ok(sha256::Hash::from_str(sha256)?)
+    }
+
+    fn hash256(&mut self, hash256: &String) -> Result<hash256::Hash, Error> {
+        Ok(hash256::Hash::from_str(hash256)?)
+    }
+
+    fn ripemd160(&mut self, ripemd160: &String) -> Result<ripemd160::Hash, Error> {
+        Ok(ripemd160::Hash::from_str(ripemd160)?)
+    }
+
+    fn hash160(&mut self, hash160: &String) -> Result<hash160::Hash, Error> {
+        Ok(hash160::Hash::from_str(hash160)?)
+    }
+}
+
+/// The "nothing up my sleeve" key of BIP341, with no known private key.
+fn unspendable_key() -> DescriptorPublicKey {
+    DescriptorPublicKey::from_str("50929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0")
+        .expect("valid x-only key")
+}
+
 fn build_xpriv_descriptor<T>(
     constructor: impl Fn(Xpriv, KeychainKind) -> T,
     xprv: Xpriv,
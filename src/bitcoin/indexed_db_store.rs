@@ -0,0 +1,234 @@
+use bdk_wallet::{chain::Merge, ChangeSet as BdkChangeSet};
+use js_sys::{Array, Promise, Reflect};
+use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    DomException, IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbRequest, IdbTransaction, IdbTransactionMode,
+};
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ChangeSet, ErrorCode},
+};
+
+use super::persister::persister_error;
+
+const DATABASE_NAME: &str = "bdk_wallet";
+const DATABASE_VERSION: u32 = 1;
+const CHANGESETS: &str = "changesets";
+
+const DEFAULT_COMPACTION_THRESHOLD: u32 = 100;
+
+/// A `WalletPersister` keeping the wallet's [`ChangeSet`]s in the browser's IndexedDB.
+///
+/// Each persisted changeset is appended as a new record keyed by the wallet id, so writes stay cheap
+/// as the wallet grows. The records are merged back into a single one once their number reaches the
+/// compaction threshold.
+#[wasm_bindgen]
+pub struct IndexedDbStore {
+    db: IdbDatabase,
+    wallet_id: String,
+    compaction_threshold: u32,
+    // Sequence number of the next record and number of records of the wallet.
+    next_seq: u32,
+    records: u32,
+}
+
+#[wasm_bindgen]
+impl IndexedDbStore {
+    /// Open the store of the wallet `wallet_id`.
+    ///
+    /// The records are compacted once there are `compaction_threshold` of them, 100 by default.
+    pub async fn open(wallet_id: String, compaction_threshold: Option<u32>) -> JsResult<IndexedDbStore> {
+        let factory: IdbFactory = Reflect::get(&js_sys::global(), &"indexedDB".into())
+            .ok()
+            .and_then(|factory| factory.dyn_into().ok())
+            .ok_or_else(|| BdkError::new(ErrorCode::Storage, "IndexedDB is not available"))?;
+
+        let open_request = factory
+            .open_with_u32(DATABASE_NAME, DATABASE_VERSION)
+            .map_err(persister_error)?;
+        let on_upgrade_needed = Closure::<dyn FnMut(JsValue)>::new({
+            let open_request = open_request.clone();
+            move |_| {
+                if let Ok(db) = open_request.result() {
+                    let _ = db.unchecked_into::<IdbDatabase>().create_object_store(CHANGESETS);
+                }
+            }
+        });
+        open_request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+        let db: IdbDatabase = request_result(&open_request).await?.unchecked_into();
+        open_request.set_onupgradeneeded(None);
+
+        let mut store = IndexedDbStore {
+            db,
+            wallet_id,
+            compaction_threshold: compaction_threshold.unwrap_or(DEFAULT_COMPACTION_THRESHOLD).max(1),
+            next_seq: 0,
+            records: 0,
+        };
+        store.read().await?;
+        Ok(store)
+    }
+
+    /// Load the stored changesets merged into one, or `undefined` if the wallet was never persisted.
+    pub async fn initialize(&mut self) -> JsResult<Option<ChangeSet>> {
+        let changeset = self.read().await?;
+        Ok((!changeset.is_empty()).then(|| changeset.into()))
+    }
+
+    /// Append `changeset` to the stored ones, compacting them if the threshold is reached.
+    pub async fn persist(&mut self, changeset: ChangeSet) -> JsResult<()> {
+        let json = changeset.to_json();
+
+        // The sequence number is read in the same transaction as the record is added, as another tab
+        // may have appended records since. `add` fails rather than overwriting an existing record.
+        let transaction = self.transaction(IdbTransactionMode::Readwrite)?;
+        let store = transaction.object_store(CHANGESETS).map_err(persister_error)?;
+        self.read_keys(&store).await?;
+        store
+            .add_with_key(&json.into(), &self.key(self.next_seq))
+            .map_err(persister_error)?;
+        transaction_complete(&transaction).await?;
+
+        self.next_seq += 1;
+        self.records += 1;
+        if self.records >= self.compaction_threshold {
+            self.compact().await?;
+        }
+        Ok(())
+    }
+
+    /// Merge the stored changesets of the wallet into a single record.
+    pub async fn compact(&mut self) -> JsResult<()> {
+        // The records are read and replaced in the same transaction, so that a record appended
+        // concurrently, e.g. from another tab, can't be deleted without having been merged.
+        let transaction = self.transaction(IdbTransactionMode::Readwrite)?;
+        let store = transaction.object_store(CHANGESETS).map_err(persister_error)?;
+        let changeset = self.read_records(&store).await?;
+        if self.records <= 1 {
+            return Ok(());
+        }
+
+        // The merged changeset replaces the last record, and all the records before it are deleted.
+        let last_seq = self.next_seq - 1;
+        let json = ChangeSet::from(changeset).to_json();
+        let previous =
+            IdbKeyRange::bound_with_lower_open_and_upper_open(&self.key(0), &self.key(last_seq), false, true)
+                .map_err(persister_error)?;
+
+        store.delete(&previous).map_err(persister_error)?;
+        store
+            .put_with_key(&json.into(), &self.key(last_seq))
+            .map_err(persister_error)?;
+        transaction_complete(&transaction).await?;
+
+        self.records = 1;
+        Ok(())
+    }
+
+    /// Delete the stored data of the wallet.
+    pub async fn clear(&mut self) -> JsResult<()> {
+        let range = self.range()?;
+        let transaction = self.transaction(IdbTransactionMode::Readwrite)?;
+        let store = transaction.object_store(CHANGESETS).map_err(persister_error)?;
+        store.delete(&range).map_err(persister_error)?;
+        transaction_complete(&transaction).await?;
+
+        self.next_seq = 0;
+        self.records = 0;
+        Ok(())
+    }
+}
+
+impl IndexedDbStore {
+    fn key(&self, seq: u32) -> JsValue {
+        Array::of2(&self.wallet_id.as_str().into(), &seq.into()).into()
+    }
+
+    /// Key range of all the records of the wallet.
+    fn range(&self) -> JsResult<IdbKeyRange> {
+        let upper = Array::of2(&self.wallet_id.as_str().into(), &f64::INFINITY.into());
+        IdbKeyRange::bound(&self.key(0), &upper).map_err(persister_error)
+    }
+
+    fn transaction(&self, mode: IdbTransactionMode) -> JsResult<IdbTransaction> {
+        self.db
+            .transaction_with_str_and_mode(CHANGESETS, mode)
+            .map_err(persister_error)
+    }
+
+    /// Read and merge the records of the wallet in a new read-only transaction.
+    async fn read(&mut self) -> JsResult<BdkChangeSet> {
+        let transaction = self.transaction(IdbTransactionMode::Readonly)?;
+        let store = transaction.object_store(CHANGESETS).map_err(persister_error)?;
+        self.read_records(&store).await
+    }
+
+    /// Read and merge the records of the wallet, updating the sequence number and record count.
+    async fn read_records(&mut self, store: &IdbObjectStore) -> JsResult<BdkChangeSet> {
+        let range = self.range()?;
+        let values_request = store.get_all_with_key(&range).map_err(persister_error)?;
+        self.read_keys(store).await?;
+        let values = Array::from(&request_result(&values_request).await?);
+
+        let mut changeset = BdkChangeSet::default();
+        for value in values.iter() {
+            let json = value
+                .as_string()
+                .ok_or_else(|| BdkError::new(ErrorCode::InvalidData, "Invalid changeset record in IndexedDB"))?;
+            changeset.merge(ChangeSet::from_json(&json)?.into());
+        }
+
+        Ok(changeset)
+    }
+
+    /// Read the keys of the records of the wallet, updating the sequence number and record count.
+    async fn read_keys(&mut self, store: &IdbObjectStore) -> JsResult<()> {
+        let range = self.range()?;
+        let request = store.get_all_keys_with_key(&range).map_err(persister_error)?;
+        let keys = Array::from(&request_result(&request).await?);
+
+        // Keys are sorted, the last one holds the highest sequence number.
+        self.records = keys.length();
+        self.next_seq = match keys.length() {
+            0 => 0,
+            n => Array::from(&keys.get(n - 1)).get(1).as_f64().unwrap_or_default() as u32 + 1,
+        };
+        Ok(())
+    }
+}
+
+/// Wait for the success of an IndexedDB request and return its result.
+async fn request_result(request: &IdbRequest) -> JsResult<JsValue> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+
+    if JsFuture::from(promise).await.is_err() {
+        return Err(dom_error(request.error().ok().flatten()));
+    }
+    request.result().map_err(persister_error)
+}
+
+/// Wait for an IndexedDB transaction to be committed.
+async fn transaction_complete(transaction: &IdbTransaction) -> JsResult<()> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        transaction.set_oncomplete(Some(&resolve));
+        transaction.set_onerror(Some(&reject));
+        transaction.set_onabort(Some(&reject));
+    });
+
+    if JsFuture::from(promise).await.is_err() {
+        return Err(dom_error(transaction.error()));
+    }
+    Ok(())
+}
+
+fn dom_error(error: Option<DomException>) -> BdkError {
+    match error {
+        Some(error) => BdkError::new(ErrorCode::Storage, format!("IndexedDB error: {}", error.message())),
+        None => BdkError::new(ErrorCode::Storage, "IndexedDB error"),
+    }
+}
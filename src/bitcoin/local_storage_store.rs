@@ -0,0 +1,66 @@
+use bdk_wallet::{chain::Merge, ChangeSet as BdkChangeSet};
+use wasm_bindgen::prelude::wasm_bindgen;
+use web_sys::Storage;
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ChangeSet, ErrorCode},
+};
+
+use super::persister::persister_error;
+
+/// A `WalletPersister` keeping the wallet's [`ChangeSet`] in the browser's `localStorage`.
+///
+/// The changes are merged into a single JSON `ChangeSet` stored under a key derived from the wallet
+/// id. Best suited for small wallets, as `localStorage` is synchronous and limited to a few megabytes.
+#[wasm_bindgen]
+pub struct LocalStorageStore {
+    storage: Storage,
+    key: String,
+}
+
+#[wasm_bindgen]
+impl LocalStorageStore {
+    #[wasm_bindgen(constructor)]
+    pub fn new(wallet_id: &str) -> JsResult<LocalStorageStore> {
+        let storage = web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| BdkError::new(ErrorCode::Storage, "localStorage is not available"))?;
+
+        Ok(LocalStorageStore {
+            storage,
+            key: format!("bdk_wallet:{wallet_id}"),
+        })
+    }
+
+    /// Load the stored changeset, or `undefined` if the wallet was never persisted.
+    pub async fn initialize(&self) -> JsResult<Option<ChangeSet>> {
+        let changeset = self.read()?;
+        Ok((!changeset.is_empty()).then(|| changeset.into()))
+    }
+
+    /// Merge `changeset` into the stored one.
+    pub async fn persist(&self, changeset: ChangeSet) -> JsResult<()> {
+        let mut stored = self.read()?;
+        stored.merge(changeset.into());
+
+        let json = ChangeSet::from(stored).to_json();
+        self.storage.set_item(&self.key, &json).map_err(persister_error)?;
+        Ok(())
+    }
+
+    /// Delete the stored data of the wallet.
+    pub fn clear(&self) -> JsResult<()> {
+        self.storage.remove_item(&self.key).map_err(persister_error)?;
+        Ok(())
+    }
+}
+
+impl LocalStorageStore {
+    fn read(&self) -> JsResult<BdkChangeSet> {
+        match self.storage.get_item(&self.key).map_err(persister_error)? {
+            Some(json) => Ok(ChangeSet::from_json(&json)?.into()),
+            None => Ok(BdkChangeSet::default()),
+        }
+    }
+}
@@ -1,9 +1,22 @@
+mod backup;
+mod coin_selection;
 mod descriptor;
+mod persister;
+mod psbt_analysis;
+mod psbt_v2;
+mod signer;
 mod tx_builder;
 mod wallet;
 mod wallet_tx;
 
+pub(crate) use backup::Backup;
+pub use coin_selection::*;
 pub use descriptor::*;
+pub use persister::*;
+pub use psbt_analysis::*;
+pub use psbt_v2::*;
+pub use signer::ExternalSigner;
+pub(crate) use signer::JsSigner;
 pub use tx_builder::*;
 pub use wallet::*;
 pub use wallet_tx::*;
@@ -13,3 +26,13 @@ mod esplora_client;
 
 #[cfg(feature = "esplora")]
 pub use esplora_client::EsploraClient;
+
+#[cfg(feature = "stores")]
+mod indexed_db_store;
+#[cfg(feature = "stores")]
+mod local_storage_store;
+
+#[cfg(feature = "stores")]
+pub use indexed_db_store::IndexedDbStore;
+#[cfg(feature = "stores")]
+pub use local_storage_store::LocalStorageStore;
@@ -0,0 +1,154 @@
+use std::{cell::RefCell, future::Future, pin::Pin, rc::Rc};
+
+use bdk_wallet::{chain::Merge, AsyncWalletPersister, ChangeSet as BdkChangeSet, Wallet as BdkWallet};
+use futures::lock::Mutex;
+use js_sys::Promise;
+use wasm_bindgen::{convert::TryFromJsValue, prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ChangeSet, ErrorCode},
+    utils::SendSyncWrapper,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const WALLET_PERSISTER: &'static str = r#"
+/**
+ * Storage backend of a persisted wallet, see `Wallet.create_persisted` and `Wallet.load_persisted`.
+ */
+export interface WalletPersister {
+  /**
+   * Load all the changes persisted so far, merged into a single changeset. Resolves to `undefined` if
+   * nothing was persisted yet.
+   */
+  initialize(): Promise<ChangeSet | undefined>;
+  /**
+   * Persist the changes staged by the wallet since the last call. The changeset must be merged into
+   * the ones already stored.
+   */
+  persist(changeset: ChangeSet): Promise<void>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS storage backend, implementing the `WalletPersister` interface.
+    #[wasm_bindgen(typescript_type = "WalletPersister")]
+    pub type WalletPersister;
+
+    #[wasm_bindgen(method, catch)]
+    fn initialize(this: &WalletPersister) -> Result<Promise, JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    fn persist(this: &WalletPersister, changeset: ChangeSet) -> Result<Promise, JsValue>;
+}
+
+/// Bridges a JS [`WalletPersister`] to BDK's [`AsyncWalletPersister`].
+struct JsPersister {
+    persister: WalletPersister,
+    // Changes taken from the wallet whose persistence failed, written again on the next attempt.
+    pending: BdkChangeSet,
+}
+
+impl AsyncWalletPersister for JsPersister {
+    type Error = BdkError;
+
+    fn initialize<'a>(
+        persister: &'a mut Self,
+    ) -> Pin<Box<dyn Future<Output = Result<BdkChangeSet, Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendSyncWrapper(async move {
+            let promise = persister.persister.initialize().map_err(persister_error)?;
+            let value = JsFuture::from(promise).await.map_err(persister_error)?;
+            if value.is_undefined() || value.is_null() {
+                return Ok(BdkChangeSet::default());
+            }
+
+            let changeset = ChangeSet::try_from_js_value(value).map_err(|_| {
+                BdkError::new(
+                    ErrorCode::Persister,
+                    "Wallet persister must initialize with a ChangeSet or undefined",
+                )
+            })?;
+            Ok(changeset.into())
+        }))
+    }
+
+    fn persist<'a>(
+        persister: &'a mut Self,
+        changeset: &'a BdkChangeSet,
+    ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'a>>
+    where
+        Self: 'a,
+    {
+        Box::pin(SendSyncWrapper(async move {
+            let promise = persister
+                .persister
+                .persist(changeset.clone().into())
+                .map_err(persister_error)?;
+            JsFuture::from(promise).await.map_err(persister_error)?;
+            Ok(())
+        }))
+    }
+}
+
+/// Writes the staged changes of a wallet to its [`WalletPersister`], one write at a time.
+pub(crate) struct Persister(Mutex<JsPersister>);
+
+impl Persister {
+    /// Initialize the persister, returning the changes persisted so far.
+    pub(crate) async fn initialize(persister: WalletPersister) -> JsResult<(Persister, BdkChangeSet)> {
+        let mut persister = JsPersister {
+            persister,
+            pending: BdkChangeSet::default(),
+        };
+        let changeset = AsyncWalletPersister::initialize(&mut persister).await?;
+        Ok((Persister(Mutex::new(persister)), changeset))
+    }
+
+    /// Persist the staged changes of `wallet`, returning whether there was any.
+    ///
+    /// If the write fails, the changes are kept and written on the next call.
+    pub(crate) async fn persist(&self, wallet: &RefCell<BdkWallet>) -> JsResult<bool> {
+        // Holding the lock keeps writes in the order the changes were staged.
+        let mut persister = self.0.lock().await;
+
+        let mut changeset = std::mem::take(&mut persister.pending);
+        if let Some(staged) = wallet.borrow_mut().take_staged() {
+            changeset.merge(staged);
+        }
+        if changeset.is_empty() {
+            return Ok(false);
+        }
+
+        match AsyncWalletPersister::persist(&mut *persister, &changeset).await {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                persister.pending = changeset;
+                Err(e)
+            }
+        }
+    }
+
+    /// Persist the staged changes of `wallet` without waiting for the write to complete.
+    ///
+    /// A failed write is retried on the next call to [`Persister::persist`].
+    pub(crate) fn persist_in_background(self: &Rc<Self>, wallet: &Rc<RefCell<BdkWallet>>) {
+        let persister = self.clone();
+        let wallet = wallet.clone();
+        spawn_local(async move {
+            let _ = persister.persist(&wallet).await;
+        });
+    }
+}
+
+pub(crate) fn persister_error(error: JsValue) -> BdkError {
+    let message = match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => error.as_string().unwrap_or_else(|| format!("{error:?}")),
+    };
+    BdkError::new(ErrorCode::Persister, format!("Wallet persister failed: {message}"))
+}
@@ -0,0 +1,540 @@
+use bdk_wallet::{
+    bitcoin::{
+        psbt::Input as BdkInput,
+        sighash::{EcdsaSighashType, TapSighashType},
+        Address as BdkAddress, Amount as BdkAmount, FeeRate as BdkFeeRate, OutPoint as BdkOutPoint, Psbt as BdkPsbt,
+        ScriptBuf as BdkScriptBuf, TxOut, Weight,
+    },
+    KeychainKind as BdkKeychainKind, Wallet as BdkWallet,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::types::{Address, Amount, FeeRate, KeychainKind, OutPoint, ScriptBuf};
+
+/// Summary of what signing a [`Psbt`](crate::types::Psbt) means for a wallet.
+///
+/// Returned by [`Wallet::analyze_psbt`](super::Wallet::analyze_psbt), typically to let the user
+/// confirm a transaction before signing it.
+#[wasm_bindgen]
+pub struct PsbtAnalysis {
+    inputs: Vec<AnalyzedInput>,
+    outputs: Vec<AnalyzedOutput>,
+    sent: Option<BdkAmount>,
+    received: Option<BdkAmount>,
+    fee: Option<BdkAmount>,
+    fee_rate: Option<BdkFeeRate>,
+    anomalies: Vec<PsbtAnomaly>,
+}
+
+#[wasm_bindgen]
+impl PsbtAnalysis {
+    /// The inputs of the transaction, in order.
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<AnalyzedInput> {
+        self.inputs.clone()
+    }
+
+    /// The outputs of the transaction, in order.
+    #[wasm_bindgen(getter)]
+    pub fn outputs(&self) -> Vec<AnalyzedOutput> {
+        self.outputs.clone()
+    }
+
+    /// Total value of the wallet's inputs, unknown if it exceeds the supply of bitcoin.
+    #[wasm_bindgen(getter)]
+    pub fn sent(&self) -> Option<Amount> {
+        self.sent.map(Into::into)
+    }
+
+    /// Total value of the outputs paying to the wallet, unknown if it exceeds the supply of bitcoin.
+    #[wasm_bindgen(getter)]
+    pub fn received(&self) -> Option<Amount> {
+        self.received.map(Into::into)
+    }
+
+    /// Net amount leaving the wallet in satoshis, fee included (`sent - received`).
+    ///
+    /// Negative if the wallet receives more than it spends, unknown if `sent` or `received` is.
+    #[wasm_bindgen(getter)]
+    pub fn net_sent(&self) -> Option<i64> {
+        // Both amounts are at most `MAX_MONEY`, which fits in an `i64`.
+        let (sent, received) = (self.sent?, self.received?);
+        Some(sent.to_sat() as i64 - received.to_sat() as i64)
+    }
+
+    /// The transaction fee, unknown if the value of an input is missing or a value is invalid.
+    #[wasm_bindgen(getter)]
+    pub fn fee(&self) -> Option<Amount> {
+        self.fee.map(Into::into)
+    }
+
+    /// The estimated fee rate once all the inputs are signed, unknown if the fee is.
+    ///
+    /// The size of the wallet's inputs is estimated from their descriptor, foreign inputs are only
+    /// accounted for once finalized.
+    #[wasm_bindgen(getter)]
+    pub fn fee_rate(&self) -> Option<FeeRate> {
+        self.fee_rate.map(Into::into)
+    }
+
+    /// Suspicious properties of the PSBT that should be reviewed before signing.
+    #[wasm_bindgen(getter)]
+    pub fn anomalies(&self) -> Vec<PsbtAnomaly> {
+        self.anomalies.clone()
+    }
+}
+
+/// Whether an input spends an output of the wallet.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InputOwnership {
+    /// The input spends an output of the wallet.
+    Ours = "ours",
+    /// The input spends an output of another wallet.
+    Foreign = "foreign",
+}
+
+/// An input of an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct AnalyzedInput {
+    index: usize,
+    previous_output: BdkOutPoint,
+    ownership: InputOwnership,
+    value: Option<BdkAmount>,
+    derivation: Option<(BdkKeychainKind, u32)>,
+}
+
+#[wasm_bindgen]
+impl AnalyzedInput {
+    /// Index of the input in the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The output spent by the input.
+    #[wasm_bindgen(getter)]
+    pub fn previous_output(&self) -> OutPoint {
+        self.previous_output.into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ownership(&self) -> InputOwnership {
+        self.ownership
+    }
+
+    /// Value of the spent output, unknown if neither the wallet nor the PSBT have it.
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<Amount> {
+        self.value.map(Into::into)
+    }
+
+    /// Keychain of the spent output, if it belongs to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn keychain(&self) -> Option<KeychainKind> {
+        self.derivation.map(|(keychain, _)| keychain.into())
+    }
+
+    /// Derivation index of the spent output, if it belongs to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn derivation_index(&self) -> Option<u32> {
+        self.derivation.map(|(_, index)| index)
+    }
+}
+
+/// Who an output pays to, from the wallet's point of view.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    /// The output pays to another wallet.
+    Recipient = "recipient",
+    /// The output pays to the wallet's internal keychain.
+    Change = "change",
+    /// The output pays to one of the wallet's external addresses.
+    OursExternal = "ours_external",
+}
+
+/// An output of an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct AnalyzedOutput {
+    index: usize,
+    script_pubkey: BdkScriptBuf,
+    address: Option<BdkAddress>,
+    value: BdkAmount,
+    kind: OutputKind,
+    derivation_index: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl AnalyzedOutput {
+    /// Index of the output in the transaction.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn script_pubkey(&self) -> ScriptBuf {
+        self.script_pubkey.clone().into()
+    }
+
+    /// The address of the output on the wallet's network, if the script has one.
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> Option<Address> {
+        self.address.clone().map(Into::into)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Amount {
+        self.value.into()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> OutputKind {
+        self.kind
+    }
+
+    /// Derivation index of the output, if it belongs to the wallet.
+    #[wasm_bindgen(getter)]
+    pub fn derivation_index(&self) -> Option<u32> {
+        self.derivation_index
+    }
+}
+
+/// Kinds of [`PsbtAnomaly`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PsbtAnomalyKind {
+    /// An output has key origins like a change output of the wallet, but its script isn't the wallet's.
+    ChangeToForeignScript = "change_to_foreign_script",
+    /// An input is, or will be, signed with a sighash type other than `ALL`.
+    UnusualSighash = "unusual_sighash",
+    /// An input has no previous output data, or data that doesn't match the spent output.
+    MissingUtxo = "missing_utxo",
+    /// The previous output data of an input is inconsistent: its witness UTXO doesn't match its
+    /// previous transaction, or the PSBT doesn't match the output known to the wallet.
+    UtxoMismatch = "utxo_mismatch",
+    /// A value, or the total value of the inputs or outputs, exceeds the supply of bitcoin.
+    InvalidAmount = "invalid_amount",
+    /// The fee rate exceeds the limit, or the fee exceeds the amount paid to the recipients.
+    AbsurdFee = "absurd_fee",
+}
+
+/// A suspicious property of an analyzed PSBT.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PsbtAnomaly {
+    kind: PsbtAnomalyKind,
+    index: Option<usize>,
+    message: String,
+}
+
+#[wasm_bindgen]
+impl PsbtAnomaly {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> PsbtAnomalyKind {
+        self.kind
+    }
+
+    /// Index of the offending input or output, if the anomaly is specific to one.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// A human readable description of the anomaly.
+    #[wasm_bindgen(getter)]
+    pub fn message(&self) -> String {
+        self.message.clone()
+    }
+}
+
+impl PsbtAnomaly {
+    fn new(kind: PsbtAnomalyKind, index: Option<usize>, message: String) -> Self {
+        PsbtAnomaly { kind, index, message }
+    }
+}
+
+/// Analyze `psbt` from the point of view of `wallet`, flagging fee rates above `max_fee_rate`.
+pub(crate) fn analyze_psbt(wallet: &BdkWallet, psbt: &BdkPsbt, max_fee_rate: BdkFeeRate) -> PsbtAnalysis {
+    let mut anomalies = Vec::new();
+
+    let mut inputs = Vec::new();
+    for (index, (txin, input)) in psbt.unsigned_tx.input.iter().zip(&psbt.inputs).enumerate() {
+        let previous_output = txin.previous_output;
+        let psbt_txout = psbt_utxo(input, previous_output);
+
+        match (&input.witness_utxo, &input.non_witness_utxo) {
+            (None, None) => anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::MissingUtxo,
+                Some(index),
+                format!("Input {index} has no previous output data"),
+            )),
+            (_, Some(tx)) if tx.compute_txid() != previous_output.txid || psbt_txout.is_none() => {
+                anomalies.push(PsbtAnomaly::new(
+                    PsbtAnomalyKind::MissingUtxo,
+                    Some(index),
+                    format!("Input {index} has a previous transaction that doesn't match {previous_output}"),
+                ))
+            }
+            _ => {}
+        }
+
+        if let Some(message) = utxo_mismatch(wallet, input, previous_output) {
+            anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::UtxoMismatch,
+                Some(index),
+                format!("Input {index} {message}"),
+            ));
+        }
+
+        if let Some(message) = unusual_sighash(input) {
+            anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::UnusualSighash,
+                Some(index),
+                format!("Input {index} {message}"),
+            ));
+        }
+
+        // Prefer the wallet's own data, which cannot be forged by the creator of the PSBT.
+        let txout = wallet.tx_graph().get_txout(previous_output).cloned().or(psbt_txout);
+        let derivation = txout
+            .as_ref()
+            .and_then(|txout| wallet.derivation_of_spk(txout.script_pubkey.clone()));
+        if let Some(txout) = txout.as_ref().filter(|txout| txout.value > BdkAmount::MAX_MONEY) {
+            anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::InvalidAmount,
+                Some(index),
+                format!("Input {index} spends {}, more than the supply of bitcoin", txout.value),
+            ));
+        }
+
+        inputs.push(AnalyzedInput {
+            index,
+            previous_output,
+            ownership: match derivation {
+                Some(_) => InputOwnership::Ours,
+                None => InputOwnership::Foreign,
+            },
+            value: txout.map(|txout| txout.value),
+            derivation,
+        });
+    }
+
+    let mut outputs = Vec::new();
+    for (index, (txout, output)) in psbt.unsigned_tx.output.iter().zip(&psbt.outputs).enumerate() {
+        let derivation = wallet.derivation_of_spk(txout.script_pubkey.clone());
+        let kind = match derivation {
+            Some((BdkKeychainKind::Internal, _)) => OutputKind::Change,
+            Some((BdkKeychainKind::External, _)) => OutputKind::OursExternal,
+            None => OutputKind::Recipient,
+        };
+
+        if kind == OutputKind::Recipient && (!output.bip32_derivation.is_empty() || !output.tap_key_origins.is_empty())
+        {
+            anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::ChangeToForeignScript,
+                Some(index),
+                format!("Output {index} is marked as change but doesn't pay to the wallet"),
+            ));
+        }
+
+        if txout.value > BdkAmount::MAX_MONEY {
+            anomalies.push(PsbtAnomaly::new(
+                PsbtAnomalyKind::InvalidAmount,
+                Some(index),
+                format!("Output {index} pays {}, more than the supply of bitcoin", txout.value),
+            ));
+        }
+
+        outputs.push(AnalyzedOutput {
+            index,
+            script_pubkey: txout.script_pubkey.clone(),
+            address: BdkAddress::from_script(&txout.script_pubkey, wallet.network()).ok(),
+            value: txout.value,
+            kind,
+            derivation_index: derivation.map(|(_, index)| index),
+        });
+    }
+
+    let sent = total(
+        inputs
+            .iter()
+            .filter(|input| input.ownership == InputOwnership::Ours)
+            .filter_map(|input| input.value),
+    );
+    let received = total(
+        outputs
+            .iter()
+            .filter(|output| output.kind != OutputKind::Recipient)
+            .map(|output| output.value),
+    );
+
+    let input_values: Option<Vec<BdkAmount>> = inputs.iter().map(|input| input.value).collect();
+    let input_value = input_values.as_ref().and_then(|values| total(values.iter().copied()));
+    let output_value = total(outputs.iter().map(|output| output.value));
+    // Values above the supply are already reported one by one.
+    if input_values.is_some_and(|values| input_value.is_none() && values.iter().all(|v| *v <= BdkAmount::MAX_MONEY)) {
+        anomalies.push(PsbtAnomaly::new(
+            PsbtAnomalyKind::InvalidAmount,
+            None,
+            "The total value of the inputs exceeds the supply of bitcoin".to_string(),
+        ));
+    }
+    if output_value.is_none() && outputs.iter().all(|output| output.value <= BdkAmount::MAX_MONEY) {
+        anomalies.push(PsbtAnomaly::new(
+            PsbtAnomalyKind::InvalidAmount,
+            None,
+            "The total value of the outputs exceeds the supply of bitcoin".to_string(),
+        ));
+    }
+
+    let fee = input_value
+        .zip(output_value)
+        .and_then(|(input_value, output_value)| input_value.checked_sub(output_value));
+    let fee_rate = fee.and_then(|fee| {
+        let weight = estimated_weight(wallet, psbt, &inputs).to_wu();
+        let fee_kwu = fee.to_sat().checked_mul(1000)?.checked_div(weight)?;
+        Some(BdkFeeRate::from_sat_per_kwu(fee_kwu))
+    });
+
+    if let Some(fee_rate) = fee_rate.filter(|fee_rate| *fee_rate > max_fee_rate) {
+        anomalies.push(PsbtAnomaly::new(
+            PsbtAnomalyKind::AbsurdFee,
+            None,
+            format!(
+                "The fee rate of {} sat/vB exceeds the limit of {} sat/vB",
+                fee_rate.to_sat_per_vb_ceil(),
+                max_fee_rate.to_sat_per_vb_ceil()
+            ),
+        ));
+    }
+
+    let recipients_value = total(
+        outputs
+            .iter()
+            .filter(|output| output.kind == OutputKind::Recipient)
+            .map(|output| output.value),
+    )
+    .unwrap_or(BdkAmount::ZERO);
+    if let Some(fee) = fee.filter(|fee| recipients_value > BdkAmount::ZERO && *fee > recipients_value) {
+        anomalies.push(PsbtAnomaly::new(
+            PsbtAnomalyKind::AbsurdFee,
+            None,
+            format!("The fee of {fee} exceeds the {recipients_value} paid to the recipients"),
+        ));
+    }
+
+    PsbtAnalysis {
+        inputs,
+        outputs,
+        sent,
+        received,
+        fee,
+        fee_rate,
+        anomalies,
+    }
+}
+
+/// The output spent by `input` according to the PSBT.
+fn psbt_utxo(input: &BdkInput, previous_output: BdkOutPoint) -> Option<TxOut> {
+    match (&input.witness_utxo, &input.non_witness_utxo) {
+        (Some(txout), _) => Some(txout.clone()),
+        (None, Some(tx)) => tx.output.get(previous_output.vout as usize).cloned(),
+        (None, None) => None,
+    }
+}
+
+/// Describes how the output data of `input` is inconsistent, if it is.
+fn utxo_mismatch(wallet: &BdkWallet, input: &BdkInput, previous_output: BdkOutPoint) -> Option<String> {
+    let witness_utxo = input.witness_utxo.as_ref();
+    let non_witness_utxo = input
+        .non_witness_utxo
+        .as_ref()
+        .filter(|tx| tx.compute_txid() == previous_output.txid)
+        .and_then(|tx| tx.output.get(previous_output.vout as usize));
+
+    if let (Some(witness_utxo), Some(non_witness_utxo)) = (witness_utxo, non_witness_utxo) {
+        if witness_utxo != non_witness_utxo {
+            return Some(format!(
+                "has a witness UTXO of {} that doesn't match the {} output of its previous transaction",
+                witness_utxo.value, non_witness_utxo.value
+            ));
+        }
+    }
+
+    let wallet_txout = wallet.tx_graph().get_txout(previous_output)?;
+    witness_utxo
+        .into_iter()
+        .chain(non_witness_utxo)
+        .find(|txout| *txout != wallet_txout)
+        .map(|txout| {
+            if txout.value == wallet_txout.value {
+                "spends an output whose script doesn't match the one known to the wallet".to_string()
+            } else {
+                format!(
+                    "spends {} according to the PSBT but {} according to the wallet",
+                    txout.value, wallet_txout.value
+                )
+            }
+        })
+}
+
+fn unusual_sighash(input: &BdkInput) -> Option<String> {
+    if let Some(sighash_type) = input.sighash_type {
+        // `SIGHASH_DEFAULT` (0) is equivalent to `SIGHASH_ALL` (1) for taproot inputs.
+        if sighash_type.to_u32() > 1 {
+            return Some(format!("requests the {sighash_type} sighash type"));
+        }
+    }
+
+    if let Some(sig) = input
+        .partial_sigs
+        .values()
+        .find(|sig| sig.sighash_type != EcdsaSighashType::All)
+    {
+        return Some(format!("has a signature with the {} sighash type", sig.sighash_type));
+    }
+
+    input
+        .tap_key_sig
+        .iter()
+        .chain(input.tap_script_sigs.values())
+        .find(|sig| !matches!(sig.sighash_type, TapSighashType::Default | TapSighashType::All))
+        .map(|sig| format!("has a signature with the {} sighash type", sig.sighash_type))
+}
+
+/// Sum of `amounts`, unknown if it exceeds the supply of bitcoin.
+fn total(amounts: impl IntoIterator<Item = BdkAmount>) -> Option<BdkAmount> {
+    amounts
+        .into_iter()
+        .try_fold(BdkAmount::ZERO, BdkAmount::checked_add)
+        .filter(|total| *total <= BdkAmount::MAX_MONEY)
+}
+
+/// Weight of the transaction once all the inputs of the wallet are signed.
+fn estimated_weight(wallet: &BdkWallet, psbt: &BdkPsbt, inputs: &[AnalyzedInput]) -> Weight {
+    let mut tx = psbt.unsigned_tx.clone();
+    for (txin, input) in tx.input.iter_mut().zip(&psbt.inputs) {
+        if let Some(script_sig) = &input.final_script_sig {
+            txin.script_sig = script_sig.clone();
+        }
+        if let Some(witness) = &input.final_script_witness {
+            txin.witness = witness.clone();
+        }
+    }
+
+    let mut weight = tx.weight();
+    for (input, analyzed) in psbt.inputs.iter().zip(inputs) {
+        let is_finalized = input.final_script_sig.is_some() || input.final_script_witness.is_some();
+        if let (false, Some((keychain, _))) = (is_finalized, analyzed.derivation) {
+            if let Ok(satisfaction_weight) = wallet.public_descriptor(keychain).max_weight_to_satisfy() {
+                weight += satisfaction_weight;
+            }
+        }
+    }
+
+    weight
+}
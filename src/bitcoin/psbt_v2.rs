@@ -0,0 +1,485 @@
+//! PSBT version 2 ([BIP370](https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki)) encoding.
+//!
+//! `rust-bitcoin` only understands version 0 PSBTs, so version 2 PSBTs are converted to and from the
+//! version 0 layout at the serialization boundary: a v2 PSBT is kept as a [`BdkPsbt`] with its
+//! `version` set to 2, which signers and finalizers handle like any other PSBT. The version 2 fields
+//! that have no version 0 equivalent are kept among its unknown fields.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Error};
+use bdk_wallet::bitcoin::{
+    absolute::LockTime, consensus::encode, hashes::Hash, hex::DisplayHex, psbt::raw, transaction::Version, Amount,
+    OutPoint, Psbt as BdkPsbt, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+const PSBT_MAGIC: &[u8] = b"psbt\xff";
+
+const PSBT_GLOBAL_UNSIGNED_TX: u64 = 0x00;
+const PSBT_GLOBAL_TX_VERSION: u64 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u64 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u64 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u64 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u64 = 0x06;
+const PSBT_GLOBAL_VERSION: u64 = 0xfb;
+
+const PSBT_IN_PREVIOUS_TXID: u64 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u64 = 0x0f;
+const PSBT_IN_SEQUENCE: u64 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u64 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u64 = 0x12;
+
+const PSBT_OUT_AMOUNT: u64 = 0x03;
+const PSBT_OUT_SCRIPT: u64 = 0x04;
+
+/// Inputs can be added to the transaction, see `PSBT_GLOBAL_TX_MODIFIABLE`.
+const TX_MODIFIABLE_INPUTS: u8 = 0x01;
+/// Outputs can be added to the transaction.
+const TX_MODIFIABLE_OUTPUTS: u8 = 0x02;
+/// An input is signed with `SIGHASH_SINGLE`, so its output must stay at the same index.
+const TX_MODIFIABLE_SIGHASH_SINGLE: u8 = 0x04;
+
+/// The smallest locktime value interpreted as a timestamp rather than a height.
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+// Fields only allowed in version 2 PSBTs.
+const GLOBAL_V2_FIELDS: [u64; 5] = [
+    PSBT_GLOBAL_TX_VERSION,
+    PSBT_GLOBAL_FALLBACK_LOCKTIME,
+    PSBT_GLOBAL_INPUT_COUNT,
+    PSBT_GLOBAL_OUTPUT_COUNT,
+    PSBT_GLOBAL_TX_MODIFIABLE,
+];
+const INPUT_V2_FIELDS: [u64; 5] = [
+    PSBT_IN_PREVIOUS_TXID,
+    PSBT_IN_OUTPUT_INDEX,
+    PSBT_IN_SEQUENCE,
+    PSBT_IN_REQUIRED_TIME_LOCKTIME,
+    PSBT_IN_REQUIRED_HEIGHT_LOCKTIME,
+];
+const OUTPUT_V2_FIELDS: [u64; 2] = [PSBT_OUT_AMOUNT, PSBT_OUT_SCRIPT];
+
+/// Deserialize a PSBT of version 0 or 2.
+///
+/// The fields of a version 2 PSBT that have no version 0 equivalent, such as the locktime requirements
+/// of the inputs or the modifiable flags, are kept as unknown fields so that they are serialized back.
+pub fn deserialize_psbt(bytes: &[u8]) -> Result<BdkPsbt, Error> {
+    let mut reader = MapReader::new(bytes)?;
+    let global = reader.read_map()?;
+
+    if global.u32(PSBT_GLOBAL_VERSION)?.unwrap_or(0) != 2 {
+        let psbt = BdkPsbt::deserialize(bytes)?;
+        check_no_v2_fields(&psbt)?;
+        return Ok(psbt);
+    }
+
+    if global.value(PSBT_GLOBAL_UNSIGNED_TX).is_some() {
+        bail!("PSBT_GLOBAL_UNSIGNED_TX is not allowed in PSBTv2");
+    }
+    let tx_version = global
+        .u32(PSBT_GLOBAL_TX_VERSION)?
+        .ok_or_else(|| anyhow!("Missing PSBT_GLOBAL_TX_VERSION in PSBTv2"))?;
+    let fallback_locktime = global.u32(PSBT_GLOBAL_FALLBACK_LOCKTIME)?;
+    let input_count = global
+        .compact_size(PSBT_GLOBAL_INPUT_COUNT)?
+        .ok_or_else(|| anyhow!("Missing PSBT_GLOBAL_INPUT_COUNT in PSBTv2"))?;
+    let output_count = global
+        .compact_size(PSBT_GLOBAL_OUTPUT_COUNT)?
+        .ok_or_else(|| anyhow!("Missing PSBT_GLOBAL_OUTPUT_COUNT in PSBTv2"))?;
+    if let Some(flags) = global.value(PSBT_GLOBAL_TX_MODIFIABLE) {
+        if flags.len() != 1 {
+            bail!("Invalid PSBT_GLOBAL_TX_MODIFIABLE in PSBTv2");
+        }
+    }
+
+    let mut inputs = Vec::new();
+    let mut txins = Vec::new();
+    let mut locktimes = Vec::new();
+    for index in 0..input_count {
+        let input = reader.read_map()?;
+
+        let txid = input
+            .value(PSBT_IN_PREVIOUS_TXID)
+            .ok_or_else(|| anyhow!("Missing PSBT_IN_PREVIOUS_TXID for input {index}"))?;
+        let txid = Txid::from_slice(txid).map_err(|_| anyhow!("Invalid PSBT_IN_PREVIOUS_TXID for input {index}"))?;
+        let vout = input
+            .u32(PSBT_IN_OUTPUT_INDEX)?
+            .ok_or_else(|| anyhow!("Missing PSBT_IN_OUTPUT_INDEX for input {index}"))?;
+        let sequence = input.u32(PSBT_IN_SEQUENCE)?.map_or(Sequence::MAX, Sequence);
+        locktimes.push(required_locktimes(&input, index)?);
+
+        txins.push(TxIn {
+            previous_output: OutPoint::new(txid, vout),
+            script_sig: ScriptBuf::new(),
+            sequence,
+            witness: Witness::new(),
+        });
+        // The sequence and locktime requirements stay in the map, see `serialize_psbt`.
+        inputs.push(input.without(&[PSBT_IN_PREVIOUS_TXID, PSBT_IN_OUTPUT_INDEX]));
+    }
+
+    let mut outputs = Vec::new();
+    let mut txouts = Vec::new();
+    for index in 0..output_count {
+        let output = reader.read_map()?;
+
+        let amount = output
+            .value(PSBT_OUT_AMOUNT)
+            .ok_or_else(|| anyhow!("Missing PSBT_OUT_AMOUNT for output {index}"))?;
+        let amount: [u8; 8] = amount
+            .try_into()
+            .map_err(|_| anyhow!("Invalid PSBT_OUT_AMOUNT for output {index}"))?;
+        let amount = u64::try_from(i64::from_le_bytes(amount))
+            .map_err(|_| anyhow!("Negative PSBT_OUT_AMOUNT for output {index}"))?;
+        let script = output
+            .value(PSBT_OUT_SCRIPT)
+            .ok_or_else(|| anyhow!("Missing PSBT_OUT_SCRIPT for output {index}"))?;
+
+        txouts.push(TxOut {
+            value: Amount::from_sat(amount),
+            script_pubkey: ScriptBuf::from_bytes(script.to_vec()),
+        });
+        outputs.push(output.without(&OUTPUT_V2_FIELDS));
+    }
+    reader.finish()?;
+
+    let unsigned_tx = Transaction {
+        version: Version(tx_version as i32),
+        lock_time: determine_locktime(fallback_locktime, &locktimes)?,
+        input: txins,
+        output: txouts,
+    };
+
+    // The fallback locktime and modifiable flags stay in the map.
+    let mut global = global.without(&[
+        PSBT_GLOBAL_TX_VERSION,
+        PSBT_GLOBAL_INPUT_COUNT,
+        PSBT_GLOBAL_OUTPUT_COUNT,
+        PSBT_GLOBAL_VERSION,
+    ]);
+    global.insert(PSBT_GLOBAL_UNSIGNED_TX, encode::serialize(&unsigned_tx));
+
+    let mut v0 = PSBT_MAGIC.to_vec();
+    for map in [global].iter().chain(&inputs).chain(&outputs) {
+        map.write(&mut v0);
+    }
+
+    let mut psbt = BdkPsbt::deserialize(&v0)?;
+    psbt.version = 2;
+    Ok(psbt)
+}
+
+/// Serialize a PSBT according to its version.
+///
+/// The modifiable flags of a version 2 PSBT are updated from the signatures of its inputs, as a BIP370
+/// signer would.
+pub fn serialize_psbt(psbt: &BdkPsbt) -> Vec<u8> {
+    if psbt.version != 2 {
+        return psbt.serialize();
+    }
+
+    let mut v0 = psbt.clone();
+    v0.version = 0;
+    let v0 = v0.serialize();
+
+    // A PSBT serialized by `rust-bitcoin` is always well-formed.
+    let mut reader = MapReader::new(&v0).expect("valid PSBT");
+    let mut global = reader
+        .read_map()
+        .expect("valid PSBT")
+        .without(&[PSBT_GLOBAL_UNSIGNED_TX, PSBT_GLOBAL_VERSION]);
+
+    let tx = &psbt.unsigned_tx;
+    global.insert(PSBT_GLOBAL_TX_VERSION, tx.version.0.to_le_bytes().to_vec());
+    global.insert(PSBT_GLOBAL_INPUT_COUNT, compact_size(tx.input.len() as u64));
+    global.insert(PSBT_GLOBAL_OUTPUT_COUNT, compact_size(tx.output.len() as u64));
+    if let Some(&[flags]) = global.value(PSBT_GLOBAL_TX_MODIFIABLE) {
+        global.insert(PSBT_GLOBAL_TX_MODIFIABLE, vec![modifiable_flags(psbt, flags)]);
+    }
+    global.insert(PSBT_GLOBAL_VERSION, 2u32.to_le_bytes().to_vec());
+
+    let mut bytes = PSBT_MAGIC.to_vec();
+    global.write(&mut bytes);
+
+    for txin in &tx.input {
+        let mut input = reader.read_map().expect("valid PSBT");
+        input.insert(
+            PSBT_IN_PREVIOUS_TXID,
+            txin.previous_output.txid.to_byte_array().to_vec(),
+        );
+        input.insert(PSBT_IN_OUTPUT_INDEX, txin.previous_output.vout.to_le_bytes().to_vec());
+        // The sequence defaults to final, only write it if it was given or changed.
+        if input.value(PSBT_IN_SEQUENCE).is_some() || txin.sequence != Sequence::MAX {
+            input.insert(PSBT_IN_SEQUENCE, txin.sequence.0.to_le_bytes().to_vec());
+        }
+        input.write(&mut bytes);
+    }
+
+    for txout in &tx.output {
+        let mut output = reader.read_map().expect("valid PSBT");
+        output.insert(PSBT_OUT_AMOUNT, (txout.value.to_sat() as i64).to_le_bytes().to_vec());
+        output.insert(PSBT_OUT_SCRIPT, txout.script_pubkey.to_bytes());
+        output.write(&mut bytes);
+    }
+
+    bytes
+}
+
+/// Change the version of a PSBT to 0 or 2, adding or removing the fields of version 2 PSBTs.
+///
+/// A PSBT converted to version 2 keeps its locktime as fallback locktime, and is flagged as modifiable
+/// until its inputs are signed.
+pub fn set_psbt_version(psbt: &mut BdkPsbt, version: u32) {
+    if psbt.version == version {
+        return;
+    }
+
+    if version == 2 {
+        let mut insert = |key_type: u64, value: Vec<u8>| {
+            psbt.unknown.insert(raw_key(key_type), value);
+        };
+        insert(
+            PSBT_GLOBAL_FALLBACK_LOCKTIME,
+            psbt.unsigned_tx.lock_time.to_consensus_u32().to_le_bytes().to_vec(),
+        );
+        insert(
+            PSBT_GLOBAL_TX_MODIFIABLE,
+            vec![TX_MODIFIABLE_INPUTS | TX_MODIFIABLE_OUTPUTS],
+        );
+    } else {
+        retain_v0_fields(&mut psbt.unknown, &GLOBAL_V2_FIELDS);
+        for input in &mut psbt.inputs {
+            retain_v0_fields(&mut input.unknown, &INPUT_V2_FIELDS);
+        }
+        for output in &mut psbt.outputs {
+            retain_v0_fields(&mut output.unknown, &OUTPUT_V2_FIELDS);
+        }
+    }
+    psbt.version = version;
+}
+
+/// The modifiable `flags` of a version 2 PSBT, updated from the signatures of its inputs.
+///
+/// Finalized inputs no longer carry their signatures, they are handled as signed with `SIGHASH_ALL`.
+fn modifiable_flags(psbt: &BdkPsbt, mut flags: u8) -> u8 {
+    const SIGHASH_ALL: u32 = 0x01;
+    const SIGHASH_NONE: u32 = 0x02;
+    const SIGHASH_SINGLE: u32 = 0x03;
+    const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+    let sighashes = psbt.inputs.iter().flat_map(|input| {
+        let finalized = input.final_script_sig.is_some() || input.final_script_witness.is_some();
+        input
+            .partial_sigs
+            .values()
+            .map(|sig| sig.sighash_type as u32)
+            .chain(input.tap_key_sig.iter().map(|sig| sig.sighash_type as u32))
+            .chain(input.tap_script_sigs.values().map(|sig| sig.sighash_type as u32))
+            .chain(finalized.then_some(SIGHASH_ALL))
+    });
+
+    for sighash in sighashes {
+        if sighash & SIGHASH_ANYONECANPAY == 0 {
+            flags &= !TX_MODIFIABLE_INPUTS;
+        }
+        // `SIGHASH_DEFAULT` (0) commits to all the outputs like `SIGHASH_ALL`.
+        match sighash & !SIGHASH_ANYONECANPAY {
+            SIGHASH_NONE => {}
+            SIGHASH_SINGLE => {
+                flags &= !TX_MODIFIABLE_OUTPUTS;
+                flags |= TX_MODIFIABLE_SIGHASH_SINGLE;
+            }
+            _ => flags &= !TX_MODIFIABLE_OUTPUTS,
+        }
+    }
+
+    flags
+}
+
+/// Fail if a version 0 PSBT has fields that only exist in version 2.
+fn check_no_v2_fields(psbt: &BdkPsbt) -> Result<(), Error> {
+    let has_v2_fields = |unknown: &BTreeMap<raw::Key, Vec<u8>>, fields: &[u64]| {
+        unknown.keys().any(|key| fields.contains(&(key.type_value as u64)))
+    };
+
+    if has_v2_fields(&psbt.unknown, &GLOBAL_V2_FIELDS)
+        || psbt
+            .inputs
+            .iter()
+            .any(|input| has_v2_fields(&input.unknown, &INPUT_V2_FIELDS))
+        || psbt
+            .outputs
+            .iter()
+            .any(|output| has_v2_fields(&output.unknown, &OUTPUT_V2_FIELDS))
+    {
+        bail!("PSBTv0 must not have PSBTv2 fields");
+    }
+    Ok(())
+}
+
+fn retain_v0_fields(unknown: &mut BTreeMap<raw::Key, Vec<u8>>, v2_fields: &[u64]) {
+    unknown.retain(|key, _| !v2_fields.contains(&(key.type_value as u64)));
+}
+
+fn raw_key(key_type: u64) -> raw::Key {
+    raw::Key {
+        type_value: key_type as u8,
+        key: Vec::new(),
+    }
+}
+
+/// The time and height locktimes required by an input, checked against the locktime threshold.
+fn required_locktimes(input: &Map, index: u64) -> Result<(Option<u32>, Option<u32>), Error> {
+    let time = input.u32(PSBT_IN_REQUIRED_TIME_LOCKTIME)?;
+    let height = input.u32(PSBT_IN_REQUIRED_HEIGHT_LOCKTIME)?;
+
+    if time.is_some_and(|time| time < LOCKTIME_THRESHOLD) {
+        bail!("PSBT_IN_REQUIRED_TIME_LOCKTIME of input {index} is below {LOCKTIME_THRESHOLD}");
+    }
+    if height.is_some_and(|height| height >= LOCKTIME_THRESHOLD) {
+        bail!("PSBT_IN_REQUIRED_HEIGHT_LOCKTIME of input {index} is not below {LOCKTIME_THRESHOLD}");
+    }
+    Ok((time, height))
+}
+
+/// Locktime determination as specified by BIP370, from the `(time, height)` locktimes required by each
+/// input.
+fn determine_locktime(fallback: Option<u32>, locktimes: &[(Option<u32>, Option<u32>)]) -> Result<LockTime, Error> {
+    let locktimes: Vec<_> = locktimes
+        .iter()
+        .filter(|(time, height)| time.is_some() || height.is_some())
+        .collect();
+    if locktimes.is_empty() {
+        return Ok(LockTime::from_consensus(fallback.unwrap_or(0)));
+    }
+
+    // Heights are preferred when every input with a requirement supports them.
+    if locktimes.iter().all(|(_, height)| height.is_some()) {
+        let height = locktimes.iter().filter_map(|(_, height)| *height).max().unwrap_or(0);
+        return Ok(LockTime::from_height(height)?);
+    }
+
+    if locktimes.iter().all(|(time, _)| time.is_some()) {
+        let time = locktimes.iter().filter_map(|(time, _)| *time).max().unwrap_or(0);
+        return Ok(LockTime::from_time(time)?);
+    }
+
+    bail!("Inputs of the PSBTv2 have incompatible locktime requirements")
+}
+
+fn compact_size(value: u64) -> Vec<u8> {
+    encode::serialize(&encode::VarInt(value))
+}
+
+/// The key-value pairs of a PSBT map, keys include their type.
+struct Map(Vec<(Vec<u8>, Vec<u8>)>);
+
+impl Map {
+    fn key_type(key: &[u8]) -> Option<u64> {
+        encode::deserialize_partial::<encode::VarInt>(key)
+            .ok()
+            .map(|(key_type, _)| key_type.0)
+    }
+
+    /// Value of the key of the given type without key data.
+    fn value(&self, key_type: u64) -> Option<&[u8]> {
+        let key = compact_size(key_type);
+        self.0.iter().find(|(k, _)| *k == key).map(|(_, v)| v.as_slice())
+    }
+
+    fn u32(&self, key_type: u64) -> Result<Option<u32>, Error> {
+        self.value(key_type)
+            .map(|value| {
+                let value: [u8; 4] = value
+                    .try_into()
+                    .map_err(|_| anyhow!("Invalid PSBT value for key type {key_type:#04x}"))?;
+                Ok(u32::from_le_bytes(value))
+            })
+            .transpose()
+    }
+
+    fn compact_size(&self, key_type: u64) -> Result<Option<u64>, Error> {
+        self.value(key_type)
+            .map(|value| {
+                encode::deserialize::<encode::VarInt>(value)
+                    .map(|value| value.0)
+                    .map_err(|_| anyhow!("Invalid PSBT value for key type {key_type:#04x}"))
+            })
+            .transpose()
+    }
+
+    fn insert(&mut self, key_type: u64, value: Vec<u8>) {
+        let key = compact_size(key_type);
+        self.0.retain(|(k, _)| *k != key);
+        self.0.push((key, value));
+    }
+
+    fn without(self, key_types: &[u64]) -> Map {
+        Map(self
+            .0
+            .into_iter()
+            .filter(|(key, _)| !Map::key_type(key).is_some_and(|key_type| key_types.contains(&key_type)))
+            .collect())
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let mut pairs: Vec<_> = self.0.iter().collect();
+        pairs.sort();
+        for (key, value) in pairs {
+            bytes.extend(compact_size(key.len() as u64));
+            bytes.extend(key);
+            bytes.extend(compact_size(value.len() as u64));
+            bytes.extend(value);
+        }
+        bytes.push(0x00);
+    }
+}
+
+/// Splits a serialized PSBT into its maps.
+struct MapReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> MapReader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        match bytes.strip_prefix(PSBT_MAGIC) {
+            Some(bytes) => Ok(MapReader { bytes }),
+            None => bail!("Invalid PSBT magic bytes"),
+        }
+    }
+
+    fn read_slice(&mut self) -> Result<&'a [u8], Error> {
+        let (len, consumed) =
+            encode::deserialize_partial::<encode::VarInt>(self.bytes).map_err(|_| anyhow!("Unexpected end of PSBT"))?;
+        let rest = &self.bytes[consumed..];
+        let len = usize::try_from(len.0).map_err(|_| anyhow!("Invalid PSBT length"))?;
+        if rest.len() < len {
+            bail!("Unexpected end of PSBT");
+        }
+        let (slice, rest) = rest.split_at(len);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    fn read_map(&mut self) -> Result<Map, Error> {
+        let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        loop {
+            let key = self.read_slice()?;
+            if key.is_empty() {
+                return Ok(Map(pairs));
+            }
+            if pairs.iter().any(|(k, _)| k == key) {
+                bail!("Duplicate PSBT key {}", key.to_lower_hex_string());
+            }
+            let value = self.read_slice()?;
+            pairs.push((key.to_vec(), value.to_vec()));
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if !self.bytes.is_empty() {
+            bail!("Unexpected trailing data in PSBT");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use std::{fmt, str::FromStr, sync::Mutex};
+
+use bdk_wallet::{
+    bitcoin::{
+        bip32::Fingerprint,
+        secp256k1::{All, Secp256k1},
+        Psbt as BdkPsbt,
+    },
+    signer::{SignerCommon, SignerError, SignerId, TransactionSigner},
+    SignOptions as BdkSignOptions,
+};
+use js_sys::Promise;
+use wasm_bindgen::{convert::TryFromJsValue, prelude::wasm_bindgen, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ErrorCode, Psbt},
+    utils::SendSyncWrapper,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const EXTERNAL_SIGNER: &'static str = r#"
+/**
+ * A signer holding its keys outside of the wallet, such as a hardware wallet, see `Wallet.add_signer`.
+ */
+export interface ExternalSigner {
+  /**
+   * Sign the inputs of `psbt` spending from the keys of the signer, resolving to the signed PSBT.
+   * Inputs may also be finalized.
+   */
+  sign(psbt: Psbt): Promise<Psbt>;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JS signer, implementing the `ExternalSigner` interface.
+    #[wasm_bindgen(typescript_type = "ExternalSigner")]
+    pub type ExternalSigner;
+
+    #[wasm_bindgen(method, catch)]
+    fn sign(this: &ExternalSigner, psbt: Psbt) -> Result<Promise, JsValue>;
+}
+
+/// Bridges an [`ExternalSigner`] to BDK's [`TransactionSigner`].
+///
+/// BDK signs synchronously, so the JS signer is called beforehand by [`JsSigner::request_signatures`]
+/// and the PSBT it returns is merged when the wallet calls [`TransactionSigner::sign_transaction`].
+pub(crate) struct JsSigner {
+    fingerprint: Fingerprint,
+    signer: SendSyncWrapper<ExternalSigner>,
+    // PSBT signed by the JS signer, waiting to be merged.
+    signed: Mutex<Option<BdkPsbt>>,
+}
+
+impl JsSigner {
+    pub(crate) fn new(fingerprint: &str, signer: ExternalSigner) -> JsResult<Self> {
+        let fingerprint = Fingerprint::from_str(fingerprint).map_err(|e| {
+            BdkError::new(
+                ErrorCode::InvalidData,
+                format!("Invalid fingerprint {fingerprint}: {e}"),
+            )
+        })?;
+
+        Ok(JsSigner {
+            fingerprint,
+            signer: SendSyncWrapper(signer),
+            signed: Mutex::new(None),
+        })
+    }
+
+    pub(crate) fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// Whether an input of `psbt` is derived from the master key of the signer.
+    pub(crate) fn can_sign(&self, psbt: &BdkPsbt) -> bool {
+        psbt.inputs.iter().any(|input| {
+            input
+                .bip32_derivation
+                .values()
+                .any(|(fingerprint, _)| *fingerprint == self.fingerprint)
+                || input
+                    .tap_key_origins
+                    .values()
+                    .any(|(_, (fingerprint, _))| *fingerprint == self.fingerprint)
+        })
+    }
+
+    /// Ask the JS signer to sign `psbt`, keeping the signed PSBT until the wallet signs.
+    pub(crate) async fn request_signatures(&self, psbt: &BdkPsbt) -> JsResult<()> {
+        let promise = self.signer.0.sign(psbt.clone().into()).map_err(|e| self.error(e))?;
+        let value = JsFuture::from(promise).await.map_err(|e| self.error(e))?;
+
+        let signed = Psbt::try_from_js_value(value).map_err(|_| {
+            BdkError::new(
+                ErrorCode::Signer,
+                format!("External signer {} must resolve to a Psbt", self.fingerprint),
+            )
+            .with("fingerprint", self.fingerprint.to_string())
+        })?;
+        if signed.unsigned_tx.compute_txid() != psbt.unsigned_tx.compute_txid() {
+            return Err(BdkError::new(
+                ErrorCode::Signer,
+                format!(
+                    "External signer {} returned a PSBT of another transaction",
+                    self.fingerprint
+                ),
+            )
+            .with("fingerprint", self.fingerprint.to_string()));
+        }
+
+        *self.signed.lock().expect("not poisoned") = Some(signed.into());
+        Ok(())
+    }
+
+    /// Drop the signed PSBT that was not merged.
+    pub(crate) fn discard(&self) {
+        self.signed.lock().expect("not poisoned").take();
+    }
+
+    fn error(&self, error: JsValue) -> BdkError {
+        let message = match error.dyn_ref::<js_sys::Error>() {
+            Some(error) => String::from(error.message()),
+            None => error.as_string().unwrap_or_else(|| format!("{error:?}")),
+        };
+        BdkError::new(
+            ErrorCode::Signer,
+            format!("External signer {} failed: {message}", self.fingerprint),
+        )
+        .with("fingerprint", self.fingerprint.to_string())
+    }
+}
+
+impl fmt::Debug for JsSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JsSigner")
+            .field("fingerprint", &self.fingerprint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SignerCommon for JsSigner {
+    fn id(&self, _secp: &Secp256k1<All>) -> SignerId {
+        SignerId::Fingerprint(self.fingerprint)
+    }
+}
+
+impl TransactionSigner for JsSigner {
+    fn sign_transaction(
+        &self,
+        psbt: &mut BdkPsbt,
+        _sign_options: &BdkSignOptions,
+        _secp: &Secp256k1<All>,
+    ) -> Result<(), SignerError> {
+        // Nothing was requested when signing synchronously.
+        if let Some(signed) = self.signed.lock().expect("not poisoned").take() {
+            psbt.combine(signed).map_err(|e| SignerError::External(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
@@ -1,13 +1,25 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
-use bdk_wallet::{bitcoin::ScriptBuf, Wallet as BdkWallet};
+use bdk_wallet::{
+    bitcoin::{OutPoint as BdkOutPoint, Psbt as BdkPsbt, ScriptBuf, Txid},
+    coin_selection::{
+        BranchAndBoundCoinSelection, CoinSelectionAlgorithm, LargestFirstCoinSelection, OldestFirstCoinSelection,
+        SingleRandomDraw,
+    },
+    ChangeSpendPolicy as BdkChangeSpendPolicy, TxBuilder as BdkTxBuilder, Wallet as BdkWallet,
+};
+use js_sys::Function;
 use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::{
+    bitcoin::{set_psbt_version, CoinSelection, JsCoinSelection, LowestWasteCoinSelection, Persister},
     result::JsResult,
-    types::{Address, FeeRate, OutPoint, Psbt, Recipient},
+    types::{Address, Amount, BdkError, ErrorCode, FeeRate, KeychainKind, OutPoint, PolicyPath, Psbt, Recipient},
 };
 
+// Size in vbytes of a P2WPKH change output, same as BDK's default for branch and bound.
+const CHANGE_OUTPUT_SIZE: u64 = 8 + 1 + 22;
+
 /// A transaction builder.
 ///
 /// A `TxBuilder` is created by calling [`build_tx`] or [`build_fee_bump`] on a wallet. After
@@ -18,30 +30,67 @@ use crate::{
 #[wasm_bindgen]
 pub struct TxBuilder {
     wallet: Rc<RefCell<BdkWallet>>,
+    persister: Option<Rc<Persister>>,
     recipients: Vec<Recipient>,
+    utxos: Vec<OutPoint>,
     unspendable: Vec<OutPoint>,
-    fee_rate: FeeRate,
+    manually_selected_only: bool,
+    change_policy: ChangeSpendPolicy,
+    fee_rate: Option<FeeRate>,
+    fee_absolute: Option<Amount>,
     drain_wallet: bool,
     drain_to: Option<ScriptBuf>,
     allow_dust: bool,
+    bump_fee: Option<Txid>,
+    coin_selection: CoinSelection,
+    coin_selection_fn: Option<Function>,
+    long_term_fee_rate: Option<FeeRate>,
+    psbt_version: u32,
+    policy_paths: Vec<(KeychainKind, BTreeMap<String, Vec<usize>>)>,
 }
 
 #[wasm_bindgen]
 impl TxBuilder {
     // We make this constructor only visible to the crate to hide the use of the `Rc<RefCell<BdkWallet>>` in `Wallet::build_tx`.
-    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>) -> TxBuilder {
+    pub(crate) fn new(wallet: Rc<RefCell<BdkWallet>>, persister: Option<Rc<Persister>>) -> TxBuilder {
         TxBuilder {
             wallet,
+            persister,
             recipients: vec![],
+            utxos: vec![],
             unspendable: vec![],
-            fee_rate: FeeRate::new(1),
+            manually_selected_only: false,
+            change_policy: ChangeSpendPolicy::ChangeAllowed,
+            fee_rate: None,
+            fee_absolute: None,
             drain_wallet: false,
             allow_dust: false,
             drain_to: None,
+            bump_fee: None,
+            coin_selection: CoinSelection::BranchAndBound,
+            coin_selection_fn: None,
+            long_term_fee_rate: None,
+            psbt_version: 0,
+            policy_paths: vec![],
+        }
+    }
+
+    // Same as `new`, but the builder replaces the transaction `txid` when finished. See `Wallet::build_fee_bump`.
+    pub(crate) fn new_fee_bump(
+        wallet: Rc<RefCell<BdkWallet>>,
+        persister: Option<Rc<Persister>>,
+        txid: Txid,
+    ) -> TxBuilder {
+        TxBuilder {
+            bump_fee: Some(txid),
+            ..TxBuilder::new(wallet, persister)
         }
     }
 
     /// Replace the recipients already added with a new list
+    ///
+    /// When bumping the fee of a transaction, the recipients default to the outputs of the original
+    /// transaction (minus the change output).
     pub fn set_recipients(mut self, recipients: Vec<Recipient>) -> Self {
         self.recipients = recipients;
         self
@@ -53,6 +102,33 @@ impl TxBuilder {
         self
     }
 
+    /// Add the list of outpoints to the internal list of UTXOs that **must** be spent.
+    ///
+    /// These have priority over the "unspendable" utxos, meaning that if a utxo is present both in
+    /// the "utxos" and the "unspendable" list, it will be spent.
+    pub fn add_utxos(mut self, outpoints: Vec<OutPoint>) -> Self {
+        self.utxos.extend(outpoints);
+        self
+    }
+
+    /// Add a utxo to the internal list of utxos that **must** be spent
+    ///
+    /// These have priority over the "unspendable" utxos, meaning that if a utxo is present both in
+    /// the "utxos" and the "unspendable" list, it will be spent.
+    pub fn add_utxo(mut self, outpoint: OutPoint) -> Self {
+        self.utxos.push(outpoint);
+        self
+    }
+
+    /// Only spend utxos added by [`add_utxo`].
+    ///
+    /// The wallet will **not** add additional utxos to the transaction even if they are needed to
+    /// make the transaction valid.
+    pub fn manually_selected_only(mut self) -> Self {
+        self.manually_selected_only = true;
+        self
+    }
+
     /// Replace the internal list of unspendable utxos with a new list
     pub fn unspendable(mut self, unspendable: Vec<OutPoint>) -> Self {
         self.unspendable = unspendable;
@@ -75,8 +151,57 @@ impl TxBuilder {
     /// Note that this is really a minimum feerate -- it's possible to
     /// overshoot it slightly since adding a change output to drain the remaining
     /// excess might not be viable.
+    ///
+    /// Overrides any absolute fee previously set with [`TxBuilder::fee_absolute`].
     pub fn fee_rate(mut self, fee_rate: FeeRate) -> Self {
-        self.fee_rate = fee_rate;
+        self.fee_rate = Some(fee_rate);
+        self.fee_absolute = None;
+        self
+    }
+
+    /// Set an absolute fee.
+    ///
+    /// The `fee_absolute` method refers to the absolute transaction fee in [`Amount`].
+    /// If anyone sets both the `fee_absolute` method and the `fee_rate` method,
+    /// the last one called wins.
+    ///
+    /// Note that this is really a minimum absolute fee -- it's possible to
+    /// overshoot it slightly since adding a change output to drain the remaining
+    /// excess might not be viable.
+    ///
+    /// When bumping the fee of a transaction, this must be higher than the fee of the
+    /// original transaction.
+    pub fn fee_absolute(mut self, fee_absolute: Amount) -> Self {
+        self.fee_absolute = Some(fee_absolute);
+        self.fee_rate = None;
+        self
+    }
+
+    /// Do not spend change outputs
+    ///
+    /// This effectively adds all the change outputs to the "unspendable" list. See
+    /// [`TxBuilder::unspendable`]. This method assumes the presence of an internal
+    /// keychain, otherwise it has no effect.
+    pub fn do_not_spend_change(mut self) -> Self {
+        self.change_policy = ChangeSpendPolicy::ChangeForbidden;
+        self
+    }
+
+    /// Only spend change outputs
+    ///
+    /// This effectively adds all the non-change outputs to the "unspendable" list. See
+    /// [`TxBuilder::unspendable`]. This method assumes the presence of an internal
+    /// keychain, otherwise it has no effect.
+    pub fn only_spend_change(mut self) -> Self {
+        self.change_policy = ChangeSpendPolicy::OnlyChange;
+        self
+    }
+
+    /// Set a specific [`ChangeSpendPolicy`]. See [`TxBuilder::do_not_spend_change`] and
+    /// [`TxBuilder::only_spend_change`] for some shortcuts. This method assumes the presence
+    /// of an internal keychain, otherwise it has no effect.
+    pub fn change_policy(mut self, change_policy: ChangeSpendPolicy) -> Self {
+        self.change_policy = change_policy;
         self
     }
 
@@ -110,19 +235,151 @@ impl TxBuilder {
         self
     }
 
+    /// Choose the coin selection algorithm.
+    ///
+    /// Default is [`CoinSelection::BranchAndBound`].
+    pub fn coin_selection(mut self, coin_selection: CoinSelection) -> Self {
+        self.coin_selection = coin_selection;
+        self
+    }
+
+    /// Use a custom coin selection algorithm implemented in JavaScript.
+    ///
+    /// `callback` is called with `(candidates: LocalOutput[], target: Amount, fee_rate: FeeRate)` and must
+    /// return the outpoints to spend among the candidates, as `OutPoint`s or `"txid:vout"` strings. UTXOs
+    /// added with [`TxBuilder::add_utxos`] are always spent on top of the returned ones. The fee and change
+    /// output are then computed from the selection, failing if it doesn't cover the target amount and fees.
+    ///
+    /// Takes precedence over [`TxBuilder::coin_selection`].
+    pub fn coin_selection_fn(mut self, callback: Function) -> Self {
+        self.coin_selection_fn = Some(callback);
+        self
+    }
+
+    /// Set the long-term fee rate, i.e. the fee rate the wallet expects to pay on average in the future.
+    ///
+    /// When set, [`CoinSelection::BranchAndBound`] falls back to the selection with the lowest waste
+    /// among the other algorithms, instead of a single random draw, when no changeless solution is found.
+    /// Selections spending more inputs are preferred when `fee_rate` is below the long-term fee rate,
+    /// and fewer inputs when it is above.
+    ///
+    /// Only used by [`CoinSelection::BranchAndBound`]: [`TxBuilder::finish`] fails with a `coin_selection`
+    /// error if another algorithm or a [`TxBuilder::coin_selection_fn`] is chosen.
+    pub fn long_term_fee_rate(mut self, long_term_fee_rate: FeeRate) -> Self {
+        self.long_term_fee_rate = Some(long_term_fee_rate);
+        self
+    }
+
+    /// Set the version of the created PSBT, either 0 (default) or 2 ([`BIP370`]).
+    ///
+    /// A version 2 PSBT is serialized as modifiable until it is signed, so that other participants
+    /// can add their own inputs and outputs.
+    pub fn psbt_version(mut self, version: u32) -> Self {
+        self.psbt_version = version;
+        self
+    }
+
+    /// Choose the branches of the spending policy of `keychain` to satisfy, by policy node id.
+    ///
+    /// Required when the descriptor has several spending paths, see [`Wallet::policies`](super::Wallet::policies).
+    /// Each entry holds the indexes of the sub-policies of a node, e.g. `{ [id]: [1] }` to spend with
+    /// the second branch of the root node `id`.
+    pub fn policy_path(mut self, policy_path: PolicyPath, keychain: KeychainKind) -> JsResult<TxBuilder> {
+        self.policy_paths.push((keychain, policy_path.to_map()?));
+        Ok(self)
+    }
+
     /// Finish building the transaction.
     ///
-    /// Returns a new [`Psbt`] per [`BIP174`].
+    /// Returns a new [`Psbt`] per [`BIP174`], or [`BIP370`] if [`TxBuilder::psbt_version`] is 2.
     pub fn finish(self) -> JsResult<Psbt> {
-        let mut wallet = self.wallet.borrow_mut();
-        let mut builder = wallet.build_tx();
+        let wallet = self.wallet.clone();
+        let persister = self.persister.clone();
+        let psbt = self.create_psbt()?;
+
+        // Creating the transaction may have revealed a change address.
+        if let Some(persister) = persister {
+            persister.persist_in_background(&wallet);
+        }
+
+        Ok(psbt.into())
+    }
+}
+
+impl TxBuilder {
+    fn create_psbt(self) -> JsResult<BdkPsbt> {
+        if self.long_term_fee_rate.is_some()
+            && (self.coin_selection != CoinSelection::BranchAndBound || self.coin_selection_fn.is_some())
+        {
+            return Err(BdkError::new(
+                ErrorCode::CoinSelection,
+                "A long-term fee rate can only be used with the branch_and_bound coin selection",
+            ));
+        }
+
+        let wallet = self.wallet.clone();
+        let mut wallet = wallet.borrow_mut();
+        let builder = match self.bump_fee {
+            Some(txid) => wallet.build_fee_bump(txid)?,
+            None => wallet.build_tx(),
+        };
+
+        if let Some(callback) = self.coin_selection_fn.clone() {
+            let coin_selection = JsCoinSelection::new(callback);
+            let error = coin_selection.error();
+            let psbt = self
+                .build(builder.coin_selection(coin_selection))
+                .map_err(|e| error.take().unwrap_or(e))?;
+            return Ok(psbt);
+        }
+
+        let psbt = match (self.coin_selection, self.long_term_fee_rate.as_deref()) {
+            (CoinSelection::LargestFirst, _) => self.build(builder.coin_selection(LargestFirstCoinSelection))?,
+            (CoinSelection::OldestFirst, _) => self.build(builder.coin_selection(OldestFirstCoinSelection))?,
+            (CoinSelection::SingleRandomDraw, _) => self.build(builder.coin_selection(SingleRandomDraw))?,
+            (_, Some(long_term_fee_rate)) => {
+                let fallback = LowestWasteCoinSelection::new(*long_term_fee_rate);
+                self.build(builder.coin_selection(BranchAndBoundCoinSelection::new(CHANGE_OUTPUT_SIZE, fallback)))?
+            }
+            (_, None) => self.build(builder)?,
+        };
+
+        Ok(psbt)
+    }
 
+    fn build<Cs: CoinSelectionAlgorithm>(self, mut builder: BdkTxBuilder<'_, Cs>) -> JsResult<BdkPsbt> {
+        if self.psbt_version != 0 && self.psbt_version != 2 {
+            return Err(BdkError::new(
+                ErrorCode::UnsupportedVersion,
+                format!("Unsupported PSBT version {}", self.psbt_version),
+            )
+            .with("version", self.psbt_version));
+        }
+
+        let utxos: Vec<BdkOutPoint> = self.utxos.into_iter().map(Into::into).collect();
         builder
-            .set_recipients(self.recipients.into_iter().map(Into::into).collect())
+            .add_utxos(&utxos)?
             .unspendable(self.unspendable.into_iter().map(Into::into).collect())
-            .fee_rate(self.fee_rate.into())
+            .change_policy(self.change_policy.into())
             .allow_dust(self.allow_dust);
 
+        if self.manually_selected_only {
+            builder.manually_selected_only();
+        }
+
+        // A fee bump starts with the recipients of the original transaction, only override them if asked to.
+        if !self.recipients.is_empty() {
+            builder.set_recipients(self.recipients.into_iter().map(Into::into).collect());
+        }
+
+        if let Some(fee_rate) = self.fee_rate {
+            builder.fee_rate(fee_rate.into());
+        }
+
+        if let Some(fee_absolute) = self.fee_absolute {
+            builder.fee_absolute(fee_absolute.into());
+        }
+
         if self.drain_wallet {
             builder.drain_wallet();
         }
@@ -131,7 +388,45 @@ impl TxBuilder {
             builder.drain_to(drain_recipient);
         }
 
-        let psbt = builder.finish()?;
-        Ok(psbt.into())
+        for (keychain, policy_path) in self.policy_paths {
+            builder.policy_path(policy_path, keychain.into());
+        }
+
+        let mut psbt = builder.finish()?;
+        set_psbt_version(&mut psbt, self.psbt_version);
+        Ok(psbt)
+    }
+}
+
+/// Policy regarding the use of change outputs when creating a transaction
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSpendPolicy {
+    /// Use both change and non-change outputs (default)
+    ChangeAllowed = "change_allowed",
+    /// Only use change outputs (see [`TxBuilder::only_spend_change`])
+    OnlyChange = "only_change",
+    /// Only use non-change outputs (see [`TxBuilder::do_not_spend_change`])
+    ChangeForbidden = "change_forbidden",
+}
+
+impl From<BdkChangeSpendPolicy> for ChangeSpendPolicy {
+    fn from(policy: BdkChangeSpendPolicy) -> Self {
+        match policy {
+            BdkChangeSpendPolicy::ChangeAllowed => ChangeSpendPolicy::ChangeAllowed,
+            BdkChangeSpendPolicy::OnlyChange => ChangeSpendPolicy::OnlyChange,
+            BdkChangeSpendPolicy::ChangeForbidden => ChangeSpendPolicy::ChangeForbidden,
+        }
+    }
+}
+
+impl From<ChangeSpendPolicy> for BdkChangeSpendPolicy {
+    fn from(policy: ChangeSpendPolicy) -> Self {
+        match policy {
+            ChangeSpendPolicy::ChangeAllowed => BdkChangeSpendPolicy::ChangeAllowed,
+            ChangeSpendPolicy::OnlyChange => BdkChangeSpendPolicy::OnlyChange,
+            ChangeSpendPolicy::ChangeForbidden => BdkChangeSpendPolicy::ChangeForbidden,
+            _ => BdkChangeSpendPolicy::ChangeAllowed,
+        }
     }
 }
@@ -1,16 +1,23 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, str::FromStr, sync::Arc};
 
-use bdk_wallet::{SignOptions, Wallet as BdkWallet};
+use bdk_wallet::{
+    bitcoin::{Amount as BdkAmount, BlockHash, Psbt as BdkPsbt},
+    chain::Merge,
+    signer::SignerOrdering,
+    LoadParams, Wallet as BdkWallet,
+};
 use js_sys::Date;
-use wasm_bindgen::{prelude::wasm_bindgen, JsError};
+use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::{
-    bitcoin::WalletTx,
+    bitcoin::{analyze_psbt, Backup, ExternalSigner, JsSigner, Persister, PsbtAnalysis, WalletPersister, WalletTx},
     result::JsResult,
     types::{
-        AddressInfo, Amount, Balance, ChangeSet, CheckPoint, FeeRate, FullScanRequest, KeychainKind, LocalOutput,
-        Network, OutPoint, Psbt, ScriptBuf, SentAndReceived, SpkIndexed, SyncRequest, Transaction, Txid, Update,
+        AddressInfo, Amount, Balance, BdkError, ChangeSet, CheckPoint, ErrorCode, FeeRate, FinalizeResult,
+        FullScanRequest, KeychainKind, LocalOutput, Network, OutPoint, Policy, Psbt, ScriptBuf, SentAndReceived,
+        SignOptions, SpkIndexed, SyncRequest, Transaction, Txid, Update,
     },
+    utils::DescriptorPair,
 };
 
 use super::TxBuilder;
@@ -19,8 +26,14 @@ use super::TxBuilder;
 // support Rust's lifetimes. This allows us to forward a reference to the
 // internal wallet when using `build_tx` and to enforce the lifetime at runtime
 // and to preserve "safe mutability".
+// The optional persister is shared the same way with the `TxBuilder`.
+// The external signers are also kept here, to request their signatures before signing.
 #[wasm_bindgen]
-pub struct Wallet(Rc<RefCell<BdkWallet>>);
+pub struct Wallet(
+    Rc<RefCell<BdkWallet>>,
+    Option<Rc<Persister>>,
+    RefCell<Vec<Arc<JsSigner>>>,
+);
 
 #[wasm_bindgen]
 impl Wallet {
@@ -29,32 +42,118 @@ impl Wallet {
             .network(network.into())
             .create_wallet_no_persist()?;
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+        Ok(Wallet::new(wallet, None))
+    }
+
+    /// Create a new wallet from a BIP389 multipath descriptor, such as `wpkh(xpub/<0;1>/*)`, whose
+    /// first path is used by the external keychain and the second by the internal keychain.
+    pub fn create_from_multipath(network: Network, descriptor: &str) -> JsResult<Wallet> {
+        let descriptors = DescriptorPair::from_multipath(descriptor)?;
+        Wallet::create(network, descriptors.external(), descriptors.internal())
     }
 
+    /// Load a wallet from its `changeset`.
+    ///
+    /// The loaded wallet is checked against each of the descriptors, `network` and `genesis_hash`
+    /// given, failing with a `load_mismatch` error if the changeset belongs to another wallet.
     pub fn load(
         changeset: ChangeSet,
         external_descriptor: Option<String>,
         internal_descriptor: Option<String>,
+        network: Option<Network>,
+        genesis_hash: Option<String>,
     ) -> JsResult<Wallet> {
-        let mut builder = BdkWallet::load();
+        let params = load_params(external_descriptor, internal_descriptor, network, genesis_hash)?;
 
-        if external_descriptor.is_some() {
-            builder = builder.descriptor(KeychainKind::External.into(), external_descriptor);
-        }
+        let wallet = match params.load_wallet_no_persist(changeset.into())? {
+            Some(wallet) => wallet,
+            None => {
+                return Err(BdkError::new(
+                    ErrorCode::InvalidData,
+                    "Cannot load a wallet from an empty changeset",
+                ))
+            }
+        };
+
+        Ok(Wallet::new(wallet, None))
+    }
 
-        if internal_descriptor.is_some() {
-            builder = builder.descriptor(KeychainKind::Internal.into(), internal_descriptor);
+    /// Load a wallet from a backup created with [`Wallet::export_encrypted_backup`].
+    pub fn load_encrypted(backup: &[u8], password: &str) -> JsResult<Wallet> {
+        let backup = Backup::decrypt(backup, password)?;
+        let changeset = ChangeSet::from_bytes(&backup.changeset.0)?;
+
+        Wallet::load(
+            changeset,
+            Some(backup.external_descriptor),
+            Some(backup.internal_descriptor),
+            None,
+            None,
+        )
+    }
+
+    /// Create a new wallet persisted with `persister`.
+    ///
+    /// The staged changes are written automatically after `apply_update`, address reveals and
+    /// `TxBuilder.finish`, see [`Wallet::persist`]. Fails if `persister` already has wallet data, use
+    /// [`Wallet::load_persisted`] instead.
+    pub async fn create_persisted(
+        network: Network,
+        external_descriptor: String,
+        internal_descriptor: String,
+        persister: WalletPersister,
+    ) -> JsResult<Wallet> {
+        let (persister, existing) = Persister::initialize(persister).await?;
+        if !existing.is_empty() {
+            return Err(BdkError::new(
+                ErrorCode::DataAlreadyExists,
+                "The persister already has wallet data, use `load_persisted` instead",
+            ));
         }
 
-        let wallet_opt = builder.extract_keys().load_wallet_no_persist(changeset.into())?;
+        let wallet = BdkWallet::create(external_descriptor, internal_descriptor)
+            .network(network.into())
+            .create_wallet_no_persist()?;
 
-        let wallet = match wallet_opt {
-            Some(wallet) => wallet,
-            None => return Err(JsError::new("Failed to load wallet, check the changeset")),
-        };
+        let wallet = Wallet::new(wallet, Some(persister));
+        wallet.persist().await?;
+        Ok(wallet)
+    }
 
-        Ok(Wallet(Rc::new(RefCell::new(wallet))))
+    /// Load a wallet persisted with `persister`, or `undefined` if it has no wallet data.
+    ///
+    /// The loaded wallet is checked as for [`Wallet::load`], and the staged changes are then written
+    /// automatically, as for [`Wallet::create_persisted`].
+    pub async fn load_persisted(
+        persister: WalletPersister,
+        external_descriptor: Option<String>,
+        internal_descriptor: Option<String>,
+        network: Option<Network>,
+        genesis_hash: Option<String>,
+    ) -> JsResult<Option<Wallet>> {
+        let params = load_params(external_descriptor, internal_descriptor, network, genesis_hash)?;
+        let (persister, changeset) = Persister::initialize(persister).await?;
+        if changeset.is_empty() {
+            return Ok(None);
+        }
+
+        let wallet_opt = params.load_wallet_no_persist(changeset)?;
+        Ok(wallet_opt.map(|wallet| Wallet::new(wallet, Some(persister))))
+    }
+
+    /// Write the staged changes with the wallet's persister, returning whether there were any.
+    ///
+    /// Changes are already written in the background after each update of the wallet. Await this
+    /// to make sure they were written or to get the error of a failed write, which is retried on the
+    /// next call. Fails if the wallet was not created with a persister.
+    pub async fn persist(&self) -> JsResult<bool> {
+        match &self.1 {
+            Some(persister) => persister.persist(&self.0).await,
+            None => Err(BdkError::new(
+                ErrorCode::Persister,
+                "The wallet has no persister, use `take_staged` instead",
+            )),
+        }
     }
 
     pub fn start_full_scan(&self) -> FullScanRequest {
@@ -71,6 +170,7 @@ impl Wallet {
 
     pub fn apply_update_at(&self, update: Update, seen_at: u64) -> JsResult<()> {
         self.0.borrow_mut().apply_update_at(update, seen_at)?;
+        self.persist_in_background();
         Ok(())
     }
 
@@ -85,7 +185,9 @@ impl Wallet {
     }
 
     pub fn next_unused_address(&self, keychain: KeychainKind) -> AddressInfo {
-        self.0.borrow_mut().next_unused_address(keychain.into()).into()
+        let address = self.0.borrow_mut().next_unused_address(keychain.into());
+        self.persist_in_background();
+        address.into()
     }
 
     pub fn peek_address(&self, keychain: KeychainKind, index: u32) -> AddressInfo {
@@ -93,15 +195,20 @@ impl Wallet {
     }
 
     pub fn reveal_next_address(&self, keychain: KeychainKind) -> AddressInfo {
-        self.0.borrow_mut().reveal_next_address(keychain.into()).into()
+        let address = self.0.borrow_mut().reveal_next_address(keychain.into());
+        self.persist_in_background();
+        address.into()
     }
 
     pub fn reveal_addresses_to(&self, keychain: KeychainKind, index: u32) -> Vec<AddressInfo> {
-        self.0
+        let addresses = self
+            .0
             .borrow_mut()
             .reveal_addresses_to(keychain.into(), index)
             .map(Into::into)
-            .collect()
+            .collect();
+        self.persist_in_background();
+        addresses
     }
 
     pub fn list_unused_addresses(&self, keychain: KeychainKind) -> Vec<AddressInfo> {
@@ -141,21 +248,141 @@ impl Wallet {
         self.0.borrow_mut().take_staged().map(Into::into)
     }
 
+    /// Export the descriptors, including their private keys, and the whole state of the wallet in a
+    /// backup encrypted with `password`.
+    ///
+    /// The encryption key is derived with Argon2id and the backup is encrypted with
+    /// XChaCha20-Poly1305. Load it back with [`Wallet::load_encrypted`].
+    pub fn export_encrypted_backup(&self, password: &str) -> JsResult<Vec<u8>> {
+        Backup::from_wallet(&self.0.borrow()).encrypt(password)
+    }
+
     pub fn public_descriptor(&self, keychain: KeychainKind) -> String {
         self.0.borrow().public_descriptor(keychain.into()).to_string()
     }
 
-    pub fn sign(&self, psbt: &mut Psbt) -> JsResult<bool> {
-        let result = self.0.borrow().sign(psbt, SignOptions::default())?;
+    /// Sign a PSBT with all the wallet's signers.
+    ///
+    /// Returns whether the PSBT was finalized. Uses the default [`SignOptions`] if `options` is omitted.
+    pub fn sign(&self, psbt: &mut Psbt, options: Option<SignOptions>) -> JsResult<bool> {
+        let options = options.unwrap_or_default();
+        let result = self.0.borrow().sign(psbt, options.into())?;
         Ok(result)
     }
 
+    /// Register an external signer for the keys derived from the master key `fingerprint`, in both
+    /// keychains. Replaces the external signer previously registered for `fingerprint`, if any.
+    ///
+    /// External signers are only called by [`Wallet::sign_async`], [`Wallet::sign`] ignores them.
+    pub fn add_signer(&self, fingerprint: &str, signer: ExternalSigner) -> JsResult<()> {
+        let signer = Arc::new(JsSigner::new(fingerprint, signer)?);
+
+        let mut wallet = self.0.borrow_mut();
+        for keychain in [KeychainKind::External, KeychainKind::Internal] {
+            wallet.add_signer(keychain.into(), SignerOrdering::default(), signer.clone());
+        }
+
+        let mut signers = self.2.borrow_mut();
+        signers.retain(|registered| registered.fingerprint() != signer.fingerprint());
+        signers.push(signer);
+        Ok(())
+    }
+
+    /// Sign a PSBT with all the wallet's signers, including the external ones.
+    ///
+    /// The external signers with keys involved in the inputs are asked to sign first, one after the
+    /// other. Their signatures are then merged and the PSBT finalized as with [`Wallet::sign`].
+    pub async fn sign_async(&self, psbt: &mut Psbt, options: Option<SignOptions>) -> JsResult<bool> {
+        let signers: Vec<_> = self
+            .2
+            .borrow()
+            .iter()
+            .filter(|signer| signer.can_sign(psbt))
+            .cloned()
+            .collect();
+
+        let mut requested = Ok(());
+        for signer in &signers {
+            requested = signer.request_signatures(psbt).await;
+            if requested.is_err() {
+                break;
+            }
+        }
+        let signed = requested.and_then(|_| self.sign(psbt, options));
+
+        // Signatures that were not merged, e.g. because signing failed before reaching their signer,
+        // must not end up in the next PSBT signed by the wallet.
+        signers.iter().for_each(|signer| signer.discard());
+        signed
+    }
+
+    /// Finalize a PSBT, i.e., for each input determine if sufficient data is available to pass
+    /// validation and construct the respective `scriptSig` or `scriptWitness`.
+    ///
+    /// Useful when signing with `try_finalize` disabled, for instance to combine the signatures of
+    /// several cosigners before finalizing. Inputs that could not be finalized are reported along with
+    /// the reason.
+    pub fn finalize_psbt(&self, psbt: &mut Psbt, options: Option<SignOptions>) -> JsResult<FinalizeResult> {
+        let options = options.unwrap_or_default();
+        let finalized = self.0.borrow().finalize_psbt(psbt, options.into())?;
+
+        let errors = if finalized { vec![] } else { psbt.finalize_errors() };
+        Ok(FinalizeResult::new(finalized, errors))
+    }
+
+    /// Summarize what signing a PSBT means for this wallet: which inputs and outputs are ours, the net
+    /// amount sent, the fee and the anomalies to review before signing.
+    ///
+    /// Fee rates above `max_fee_rate` are flagged as absurd, defaults to the limit of
+    /// [`Psbt::extract_tx`].
+    pub fn analyze_psbt(&self, psbt: &Psbt, max_fee_rate: Option<FeeRate>) -> PsbtAnalysis {
+        let max_fee_rate = max_fee_rate.map_or(BdkPsbt::DEFAULT_MAX_FEE_RATE, Into::into);
+        analyze_psbt(&self.0.borrow(), psbt, max_fee_rate)
+    }
+
+    /// The spending policy tree of the descriptor of `keychain`, or `undefined` if it has none.
+    ///
+    /// Descriptors with several spending paths require to choose one with [`TxBuilder::policy_path`].
+    pub fn policies(&self, keychain: KeychainKind) -> JsResult<Option<Policy>> {
+        let policy = self.0.borrow().policies(keychain.into())?;
+        policy.as_ref().map(Policy::try_from).transpose()
+    }
+
     pub fn derivation_index(&self, keychain: KeychainKind) -> Option<u32> {
         self.0.borrow().derivation_index(keychain.into())
     }
 
     pub fn build_tx(&self) -> TxBuilder {
-        TxBuilder::new(self.0.clone())
+        TxBuilder::new(self.0.clone(), self.1.clone())
+    }
+
+    /// Bump the fee of a transaction previously created with this wallet.
+    ///
+    /// Returns an error if the transaction is already confirmed, does not spend any of the wallet's
+    /// outputs or doesn't signal replaceability (RBF). Use [`TxBuilder::fee_rate`] or
+    /// [`TxBuilder::fee_absolute`] on the returned builder to set the new fee.
+    pub fn build_fee_bump(&self, txid: Txid) -> JsResult<TxBuilder> {
+        let txid = txid.into();
+
+        {
+            let mut wallet = self.0.borrow_mut();
+
+            if let Some(wallet_tx) = wallet.get_tx(txid) {
+                let (sent, _) = wallet.sent_and_received(&wallet_tx.tx_node.tx);
+                if sent == BdkAmount::ZERO {
+                    return Err(BdkError::new(
+                        ErrorCode::NotOwnTransaction,
+                        format!("Transaction {txid} does not spend any output of this wallet"),
+                    )
+                    .with("txid", txid));
+                }
+            }
+
+            // Validate the transaction now rather than when calling `finish`.
+            wallet.build_fee_bump(txid)?;
+        }
+
+        Ok(TxBuilder::new_fee_bump(self.0.clone(), self.1.clone(), txid))
     }
 
     pub fn calculate_fee(&self, tx: Transaction) -> JsResult<Amount> {
@@ -184,3 +411,52 @@ impl Wallet {
             .map(|(keychain, index)| SpkIndexed(keychain.into(), index))
     }
 }
+
+impl Wallet {
+    fn new(wallet: BdkWallet, persister: Option<Persister>) -> Self {
+        Wallet(
+            Rc::new(RefCell::new(wallet)),
+            persister.map(Rc::new),
+            RefCell::new(Vec::new()),
+        )
+    }
+
+    fn persist_in_background(&self) {
+        if let Some(persister) = &self.1 {
+            persister.persist_in_background(&self.0);
+        }
+    }
+}
+
+fn load_params(
+    external_descriptor: Option<String>,
+    internal_descriptor: Option<String>,
+    network: Option<Network>,
+    genesis_hash: Option<String>,
+) -> JsResult<LoadParams> {
+    let mut builder = BdkWallet::load();
+
+    if external_descriptor.is_some() {
+        builder = builder.descriptor(KeychainKind::External.into(), external_descriptor);
+    }
+
+    if internal_descriptor.is_some() {
+        builder = builder.descriptor(KeychainKind::Internal.into(), internal_descriptor);
+    }
+
+    if let Some(network) = network {
+        builder = builder.check_network(network.into());
+    }
+
+    if let Some(genesis_hash) = genesis_hash {
+        let genesis_hash = BlockHash::from_str(&genesis_hash).map_err(|e| {
+            BdkError::new(
+                ErrorCode::InvalidData,
+                format!("Invalid genesis hash {genesis_hash}: {e}"),
+            )
+        })?;
+        builder = builder.check_genesis_hash(genesis_hash);
+    }
+
+    Ok(builder.extract_keys())
+}
@@ -1,14 +1,37 @@
-use std::ops::Deref;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ops::Deref,
+    sync::Arc,
+};
 
 use bdk_wallet::{
-    chain::Merge,
-    serde_json::{from_str, to_string},
+    bitcoin::{
+        consensus::{deserialize, serialize},
+        Amount, BlockHash, Network, OutPoint, ScriptBuf, TxOut, Txid,
+    },
+    chain::{
+        local_chain::LocalChain, Anchor, BlockId, ChainOracle, ConfirmationBlockTime, DescriptorId, Merge, TxGraph,
+    },
+    descriptor::{Descriptor, DescriptorPublicKey},
+    serde_json::{from_str, from_value as from_json_value, to_string, Value},
     ChangeSet as BdkChangeSet,
 };
-use serde_wasm_bindgen::{from_value, to_value};
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use js_sys::{Array, BigInt, Map, Object};
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::to_value;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+use crate::{
+    result::JsResult,
+    types::{BdkError, ErrorCode},
+    utils::Bytes,
+};
+
+/// Version of the JSON schema of a `ChangeSet`, see [`ChangeSet::to_json`].
+const SCHEMA_VERSION: u64 = 1;
 
-use crate::result::JsResult;
+/// Version of the binary encoding of a `ChangeSet`, prefixed to the encoded bytes.
+const BINARY_ENCODING_VERSION: u8 = 1;
 
 /// A changeset for [`Wallet`].
 #[wasm_bindgen]
@@ -26,24 +49,68 @@ impl ChangeSet {
         self.0.is_empty()
     }
 
+    /// Drop the data that does not contribute to the state of a wallet loaded from this changeset.
+    ///
+    /// The changeset must hold the whole wallet state, as merged since the wallet creation. Compaction
+    /// removes checkpoints that were removed from the chain, anchors that are never used to position a
+    /// transaction, and transactions that are not canonical (replaced or conflicting) and were last
+    /// seen before `replaced_before`, a unix timestamp, or at any time if omitted. A transaction is only
+    /// removed along with all its descendants. The state of a wallet loaded from the compacted changeset
+    /// is identical.
+    pub fn compact(&mut self, replaced_before: Option<u64>) -> JsResult<()> {
+        compact(&mut self.0, replaced_before.unwrap_or(u64::MAX))
+    }
+
     /// Serialize `ChangeSet` to JSON.
+    ///
+    /// The changeset is wrapped in a `{ "version": ..., "changeset": ... }` envelope recording the
+    /// version of its schema, so it can be migrated when loaded by a later release.
     pub fn to_json(&self) -> String {
-        to_string(&self.0).expect("Serialization should not fail")
+        to_string(&Envelope::new(&self.0)).expect("Serialization should not fail")
     }
 
-    /// Serialize `ChangeSet` to JSON compatible with WASM.
+    /// Serialize `ChangeSet` to JSON compatible with WASM, in the same envelope as [`ChangeSet::to_json`].
     pub fn to_js(&self) -> JsValue {
-        to_value(&self.0).expect("Serialization should not fail")
+        to_value(&Envelope::new(&self.0)).expect("Serialization should not fail")
     }
 
     /// Create a new `ChangeSet` from a JSON string.
+    ///
+    /// Changesets persisted by older releases are migrated to the current schema, including bare
+    /// changesets serialized before the versioned envelope was introduced.
     pub fn from_json(val: &str) -> JsResult<ChangeSet> {
-        Ok(ChangeSet(from_str(val)?))
+        migrate(from_str(val)?)
+    }
+
+    /// Serialize `ChangeSet` to a compact binary encoding.
+    ///
+    /// The encoding is a version byte followed by a CBOR record where transactions are kept in their
+    /// consensus encoding. It is much smaller and faster to load than JSON for wallets with many
+    /// transactions.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![BINARY_ENCODING_VERSION];
+        ciborium::into_writer(&BinaryChangeSet::from(&self.0), &mut bytes).expect("Serialization should not fail");
+        bytes
+    }
+
+    /// Create a new `ChangeSet` from its binary encoding, see [`ChangeSet::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> JsResult<ChangeSet> {
+        match bytes.split_first() {
+            Some((&BINARY_ENCODING_VERSION, cbor)) => {
+                let changeset: BinaryChangeSet = ciborium::from_reader(cbor)?;
+                Ok(ChangeSet(changeset.try_into()?))
+            }
+            Some((version, _)) => Err(BdkError::new(
+                ErrorCode::UnsupportedVersion,
+                format!("Unsupported ChangeSet encoding version: {version}"),
+            )),
+            None => Err(BdkError::new(ErrorCode::InvalidData, "Empty ChangeSet encoding")),
+        }
     }
 
-    /// Create a new `ChangeSet` from a JS object.
+    /// Create a new `ChangeSet` from a JS object, migrating it like [`ChangeSet::from_json`].
     pub fn from_js(js_value: JsValue) -> JsResult<ChangeSet> {
-        Ok(ChangeSet(from_value(js_value)?))
+        migrate(js_to_json(&js_value)?)
     }
 }
 
@@ -66,3 +133,293 @@ impl From<ChangeSet> for BdkChangeSet {
         changeset.0
     }
 }
+
+/// Binary layout of a [`BdkChangeSet`].
+///
+/// Records are tuples rather than structs so field names are not repeated, and transactions are
+/// consensus encoded, which is both smaller and faster to decode than their serde representation.
+#[derive(Serialize, Deserialize)]
+struct BinaryChangeSet {
+    descriptor: Option<Descriptor<DescriptorPublicKey>>,
+    change_descriptor: Option<Descriptor<DescriptorPublicKey>>,
+    network: Option<Network>,
+    blocks: BTreeMap<u32, Option<BlockHash>>,
+    txs: Vec<Bytes>,
+    txouts: Vec<(Txid, u32, u64, ScriptBuf)>,
+    anchors: Vec<(u32, BlockHash, u64, Txid)>,
+    last_seen: Vec<(Txid, u64)>,
+    last_revealed: Vec<(DescriptorId, u32)>,
+}
+
+impl From<&BdkChangeSet> for BinaryChangeSet {
+    fn from(changeset: &BdkChangeSet) -> Self {
+        let tx_graph = &changeset.tx_graph;
+        BinaryChangeSet {
+            descriptor: changeset.descriptor.clone(),
+            change_descriptor: changeset.change_descriptor.clone(),
+            network: changeset.network,
+            blocks: changeset.local_chain.blocks.clone(),
+            txs: tx_graph.txs.iter().map(|tx| Bytes(serialize(tx.as_ref()))).collect(),
+            txouts: tx_graph
+                .txouts
+                .iter()
+                .map(|(outpoint, txout)| {
+                    (
+                        outpoint.txid,
+                        outpoint.vout,
+                        txout.value.to_sat(),
+                        txout.script_pubkey.clone(),
+                    )
+                })
+                .collect(),
+            anchors: tx_graph
+                .anchors
+                .iter()
+                .map(|(anchor, txid)| {
+                    (
+                        anchor.block_id.height,
+                        anchor.block_id.hash,
+                        anchor.confirmation_time,
+                        *txid,
+                    )
+                })
+                .collect(),
+            last_seen: tx_graph.last_seen.iter().map(|(txid, seen)| (*txid, *seen)).collect(),
+            last_revealed: changeset
+                .indexer
+                .last_revealed
+                .iter()
+                .map(|(id, index)| (*id, *index))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<BinaryChangeSet> for BdkChangeSet {
+    type Error = BdkError;
+
+    fn try_from(binary: BinaryChangeSet) -> JsResult<Self> {
+        let mut changeset = BdkChangeSet {
+            descriptor: binary.descriptor,
+            change_descriptor: binary.change_descriptor,
+            network: binary.network,
+            ..Default::default()
+        };
+        changeset.local_chain.blocks = binary.blocks;
+
+        let tx_graph = &mut changeset.tx_graph;
+        for tx in binary.txs {
+            tx_graph.txs.insert(Arc::new(deserialize(&tx.0)?));
+        }
+        tx_graph.txouts = binary
+            .txouts
+            .into_iter()
+            .map(|(txid, vout, value, script_pubkey)| {
+                let txout = TxOut {
+                    value: Amount::from_sat(value),
+                    script_pubkey,
+                };
+                (OutPoint::new(txid, vout), txout)
+            })
+            .collect();
+        tx_graph.anchors = binary
+            .anchors
+            .into_iter()
+            .map(|(height, hash, confirmation_time, txid)| {
+                let anchor = ConfirmationBlockTime {
+                    block_id: BlockId { height, hash },
+                    confirmation_time,
+                };
+                (anchor, txid)
+            })
+            .collect();
+        tx_graph.last_seen = binary.last_seen.into_iter().collect();
+        changeset.indexer.last_revealed = binary.last_revealed.into_iter().collect();
+
+        Ok(changeset)
+    }
+}
+
+/// Versioned envelope of a serialized [`BdkChangeSet`].
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u64,
+    changeset: &'a BdkChangeSet,
+}
+
+impl<'a> Envelope<'a> {
+    fn new(changeset: &'a BdkChangeSet) -> Self {
+        Envelope {
+            version: SCHEMA_VERSION,
+            changeset,
+        }
+    }
+}
+
+/// Migrations of the serialized changeset, `MIGRATIONS[v]` upgrades version `v` to version `v + 1`.
+const MIGRATIONS: [fn(Value) -> JsResult<Value>; SCHEMA_VERSION as usize] = [migrate_v0_to_v1];
+
+/// Version 0 is a bare changeset as serialized by `bdk_wallet` 1.x, before the envelope was introduced.
+/// The changeset layout itself did not change.
+fn migrate_v0_to_v1(changeset: Value) -> JsResult<Value> {
+    Ok(changeset)
+}
+
+/// Deserialize a changeset of any known schema version, migrating it to the current one.
+fn migrate(value: Value) -> JsResult<ChangeSet> {
+    let (version, mut changeset) = match value {
+        Value::Object(mut envelope) if envelope.contains_key("version") => {
+            let version = envelope
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| BdkError::new(ErrorCode::InvalidData, "Invalid ChangeSet schema version"))?;
+            let changeset = envelope
+                .remove("changeset")
+                .ok_or_else(|| BdkError::new(ErrorCode::InvalidData, "Missing changeset in ChangeSet envelope"))?;
+            (version, changeset)
+        }
+        changeset => (0, changeset),
+    };
+
+    if version > SCHEMA_VERSION {
+        return Err(BdkError::new(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "ChangeSet schema version {version} is not supported, the latest known version is \
+             {SCHEMA_VERSION}: upgrade this package to load it"
+            ),
+        ));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        changeset = migration(changeset)?;
+    }
+
+    Ok(ChangeSet(from_json_value(changeset)?))
+}
+
+/// Convert a JS value to JSON so it can be migrated like [`ChangeSet::from_json`].
+///
+/// Unlike `serde_wasm_bindgen`, keys of JS `Map`s are converted to strings, as produced by
+/// [`ChangeSet::to_js`] for maps with integer keys.
+fn js_to_json(value: &JsValue) -> JsResult<Value> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    if let Some(bool) = value.as_bool() {
+        return Ok(Value::Bool(bool));
+    }
+    if let Some(string) = value.as_string() {
+        return Ok(Value::String(string));
+    }
+    if let Some(number) = value.as_f64() {
+        return Ok(if number.fract() == 0.0 && number.abs() < 2f64.powi(53) {
+            Value::from(number as i64)
+        } else {
+            Value::from(number)
+        });
+    }
+    if let Some(bigint) = value.dyn_ref::<BigInt>() {
+        let digits = String::from(
+            bigint
+                .to_string(10)
+                .map_err(|_| BdkError::new(ErrorCode::InvalidData, "Invalid BigInt"))?,
+        );
+        return Ok(Value::Number(
+            digits
+                .parse()
+                .map_err(|_| BdkError::new(ErrorCode::InvalidData, "Invalid BigInt"))?,
+        ));
+    }
+    if Array::is_array(value) {
+        let array = Array::from(value);
+        return array.iter().map(|item| js_to_json(&item)).collect();
+    }
+
+    let entries = match value.dyn_ref::<Map>() {
+        Some(map) => Array::from(&map.entries()),
+        None => Object::entries(value.unchecked_ref()),
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let entry = Array::from(&entry);
+            let key = match js_to_json(&entry.get(0))? {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            Ok((key, js_to_json(&entry.get(1))?))
+        })
+        .collect()
+}
+
+fn compact(changeset: &mut BdkChangeSet, replaced_before: u64) -> JsResult<()> {
+    // The changeset is only modified once the chain is known to be valid, so that it is left untouched
+    // on error.
+    let mut local_chain = changeset.local_chain.clone();
+    local_chain.blocks.retain(|_, hash| hash.is_some());
+    let chain = LocalChain::from_changeset(local_chain.clone()).map_err(|_| {
+        BdkError::new(
+            ErrorCode::MissingGenesis,
+            "Cannot compact a ChangeSet without the genesis block, merge it first",
+        )
+    })?;
+    changeset.local_chain = local_chain;
+    let tip = chain.tip().block_id();
+    let in_chain = |block: BlockId| chain.is_block_in_chain(block, tip).expect("infallible");
+
+    let mut graph = TxGraph::<ConfirmationBlockTime>::default();
+    graph.apply_changeset(changeset.tx_graph.clone());
+    let canonical: HashSet<Txid> = graph
+        .list_canonical_txs(&chain, tip)
+        .map(|tx| tx.tx_node.txid)
+        .collect();
+
+    let tx_graph = &mut changeset.tx_graph;
+    let (replaced, mut kept): (Vec<Txid>, Vec<Txid>) =
+        tx_graph.txs.iter().map(|tx| tx.compute_txid()).partition(|txid| {
+            !canonical.contains(txid) && tx_graph.last_seen.get(txid).copied().unwrap_or_default() < replaced_before
+        });
+    // A kept transaction conflicts with the transactions its ancestors conflict with, so these are kept too.
+    let mut replaced: HashSet<Txid> = replaced.into_iter().collect();
+    while let Some(txid) = kept.pop() {
+        let Some(tx) = graph.get_tx(txid) else { continue };
+        for input in &tx.input {
+            if replaced.remove(&input.previous_output.txid) {
+                kept.push(input.previous_output.txid);
+            }
+        }
+    }
+    tx_graph.txs.retain(|tx| !replaced.contains(&tx.compute_txid()));
+    tx_graph.last_seen.retain(|txid, _| !replaced.contains(txid));
+
+    // Canonicalization positions a transaction with its first anchor in the best chain, and orders
+    // transactions by their highest anchor. Other anchors only matter while their block is unknown.
+    let mut anchors_by_tx = BTreeMap::<Txid, BTreeSet<ConfirmationBlockTime>>::new();
+    for (anchor, txid) in std::mem::take(&mut tx_graph.anchors) {
+        if !replaced.contains(&txid) {
+            anchors_by_tx.entry(txid).or_default().insert(anchor);
+        }
+    }
+    for (txid, anchors) in anchors_by_tx {
+        let first_in_chain = anchors
+            .iter()
+            .find(|anchor| in_chain(anchor.anchor_block()) == Some(true));
+        let highest = anchors.iter().map(|anchor| anchor.block_id.height).max();
+        let first_highest = match first_in_chain {
+            Some(anchor) if Some(anchor.block_id.height) == highest => None,
+            _ => anchors.iter().find(|anchor| Some(anchor.block_id.height) == highest),
+        };
+
+        for anchor in &anchors {
+            if Some(anchor) == first_in_chain
+                || Some(anchor) == first_highest
+                || in_chain(anchor.anchor_block()).is_none()
+            {
+                tx_graph.anchors.insert((*anchor, txid));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use std::{collections::BTreeSet, ops::Deref};
+
+use bdk_wallet::{
+    descriptor::{DescriptorPublicKey, ExtendedDescriptor},
+    keys::KeyMap,
+    miniscript::{descriptor::DescriptorType as BdkDescriptorType, ForEachKey},
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{bitcoin::parse_descriptor, result::JsResult};
+
+use super::{Address, BdkError, ErrorCode, Network, ScriptBuf};
+
+/// An output script descriptor, parsed and validated.
+///
+/// Private keys are kept apart from the descriptor: it is displayed with its public keys unless
+/// [`Descriptor::to_string_with_secret`] is used.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Descriptor {
+    descriptor: ExtendedDescriptor,
+    key_map: KeyMap,
+}
+
+#[wasm_bindgen]
+impl Descriptor {
+    /// Parse a descriptor, with or without its checksum, and check it is sane.
+    ///
+    /// Multipath keys, such as `xprv/84'/0'/0'/<0;1>/*`, are supported, including private ones.
+    #[wasm_bindgen(constructor)]
+    pub fn new(descriptor: &str) -> JsResult<Descriptor> {
+        let (descriptor, key_map) =
+            parse_descriptor(descriptor).map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
+        descriptor.sanity_check()?;
+
+        Ok(Descriptor { descriptor, key_map })
+    }
+
+    /// The checksum of the descriptor.
+    #[wasm_bindgen(getter)]
+    pub fn checksum(&self) -> String {
+        let descriptor = self.descriptor.to_string();
+        descriptor
+            .rsplit_once('#')
+            .map(|(_, checksum)| checksum.to_string())
+            .unwrap_or_default()
+    }
+
+    /// The type of the descriptor.
+    #[wasm_bindgen(getter)]
+    pub fn descriptor_type(&self) -> DescriptorType {
+        self.descriptor.desc_type().into()
+    }
+
+    /// Whether the descriptor has a wildcard, deriving a different script at each index.
+    #[wasm_bindgen(getter)]
+    pub fn is_ranged(&self) -> bool {
+        self.descriptor.has_wildcard()
+    }
+
+    /// Whether the descriptor has multipath keys, such as `<0;1>`, describing several descriptors.
+    #[wasm_bindgen(getter)]
+    pub fn is_multipath(&self) -> bool {
+        self.descriptor.is_multipath()
+    }
+
+    /// Whether the descriptor was parsed with private keys.
+    #[wasm_bindgen(getter)]
+    pub fn has_secrets(&self) -> bool {
+        !self.key_map.is_empty()
+    }
+
+    /// The keys of the descriptor, in order of appearance.
+    #[wasm_bindgen(getter)]
+    pub fn keys(&self) -> Vec<DescriptorKey> {
+        let mut keys = Vec::new();
+        self.descriptor.for_each_key(|key| {
+            keys.push(DescriptorKey::new(key, self.key_map.contains_key(key)));
+            true
+        });
+        keys
+    }
+
+    /// Worst-case weight of the witness and `scriptSig` satisfying the descriptor, in weight units.
+    pub fn max_weight_to_satisfy(&self) -> JsResult<u64> {
+        Ok(self.descriptor.max_weight_to_satisfy()?.to_wu())
+    }
+
+    /// The script pubkey derived at `index`. The index is ignored if the descriptor is not ranged.
+    pub fn script_pubkey(&self, index: u32) -> JsResult<ScriptBuf> {
+        let descriptor = self.descriptor.at_derivation_index(index)?;
+        Ok(descriptor.script_pubkey().into())
+    }
+
+    /// The address derived at `index` on `network`. The index is ignored if the descriptor is not ranged.
+    pub fn address(&self, index: u32, network: Network) -> JsResult<Address> {
+        let descriptor = self.descriptor.at_derivation_index(index)?;
+        Ok(descriptor.address(network.into())?.into())
+    }
+
+    /// Split a multipath descriptor into a descriptor for each of its paths, each with its private keys.
+    ///
+    /// A descriptor without multipath keys gives itself. All the multipath keys must have the same
+    /// number of paths.
+    pub fn into_single_descriptors(self) -> JsResult<Vec<Descriptor>> {
+        let mut path_counts = BTreeSet::new();
+        self.descriptor.for_each_key(|key| {
+            if let DescriptorPublicKey::MultiXPub(xpub) = key {
+                path_counts.insert(xpub.derivation_paths.paths().len());
+            }
+            true
+        });
+        if path_counts.len() > 1 {
+            return Err(BdkError::new(
+                ErrorCode::Descriptor,
+                "Multipath keys must all have the same number of paths",
+            ));
+        }
+
+        let descriptors = self.descriptor.into_single_descriptors()?;
+        let descriptors = descriptors
+            .into_iter()
+            .enumerate()
+            .map(|(index, descriptor)| {
+                let key_map = self
+                    .key_map
+                    .iter()
+                    .filter_map(|(public, secret)| match public.is_multipath() {
+                        true => Some((
+                            public.clone().into_single_keys().into_iter().nth(index)?,
+                            secret.clone().into_single_keys().into_iter().nth(index)?,
+                        )),
+                        false => Some((public.clone(), secret.clone())),
+                    })
+                    .collect();
+                Descriptor { descriptor, key_map }
+            })
+            .collect();
+
+        Ok(descriptors)
+    }
+
+    /// The descriptor with its public keys and checksum.
+    #[allow(clippy::inherent_to_string)]
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_string(&self) -> String {
+        self.descriptor.to_string()
+    }
+
+    /// The descriptor with its private keys, if any, and checksum.
+    pub fn to_string_with_secret(&self) -> String {
+        self.descriptor.to_string_with_secret(&self.key_map)
+    }
+}
+
+impl Deref for Descriptor {
+    type Target = ExtendedDescriptor;
+
+    fn deref(&self) -> &Self::Target {
+        &self.descriptor
+    }
+}
+
+/// A key of a [`Descriptor`].
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct DescriptorKey {
+    key: String,
+    fingerprint: String,
+    origin_path: Option<String>,
+    has_secret: bool,
+}
+
+impl DescriptorKey {
+    fn new(key: &DescriptorPublicKey, has_secret: bool) -> Self {
+        let origin = match key {
+            DescriptorPublicKey::Single(single) => &single.origin,
+            DescriptorPublicKey::XPub(xpub) => &xpub.origin,
+            DescriptorPublicKey::MultiXPub(xpub) => &xpub.origin,
+        };
+
+        DescriptorKey {
+            key: key.to_string(),
+            fingerprint: key.master_fingerprint().to_string(),
+            origin_path: origin.as_ref().map(|(_, path)| path.to_string()),
+            has_secret,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DescriptorKey {
+    /// The public key as written in the descriptor, with its origin and derivation steps.
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    /// The fingerprint of the master key, from the origin if any, or of the key itself.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    /// The derivation path from the master key to the key, e.g. `84'/1'/0'`, if the origin is given.
+    #[wasm_bindgen(getter)]
+    pub fn origin_path(&self) -> Option<String> {
+        self.origin_path.clone()
+    }
+
+    /// Whether the private key was given in the descriptor.
+    #[wasm_bindgen(getter)]
+    pub fn has_secret(&self) -> bool {
+        self.has_secret
+    }
+}
+
+/// The different types of descriptors.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DescriptorType {
+    /// Bare descriptor, with a raw script.
+    Bare = "bare",
+    /// Pay to script hash.
+    Sh = "sh",
+    /// Pay to pubkey hash.
+    Pkh = "pkh",
+    /// Pay to witness pubkey hash.
+    Wpkh = "wpkh",
+    /// Pay to witness script hash.
+    Wsh = "wsh",
+    /// Pay to witness pubkey hash nested in pay to script hash.
+    ShWpkh = "sh_wpkh",
+    /// Pay to witness script hash nested in pay to script hash.
+    ShWsh = "sh_wsh",
+    /// Sorted multisig in pay to witness script hash nested in pay to script hash.
+    ShWshSortedMulti = "sh_wsh_sorted_multi",
+    /// Sorted multisig in pay to script hash.
+    ShSortedMulti = "sh_sorted_multi",
+    /// Sorted multisig in pay to witness script hash.
+    WshSortedMulti = "wsh_sorted_multi",
+    /// Pay to taproot.
+    Tr = "tr",
+}
+
+impl From<BdkDescriptorType> for DescriptorType {
+    fn from(descriptor_type: BdkDescriptorType) -> Self {
+        match descriptor_type {
+            BdkDescriptorType::Bare => DescriptorType::Bare,
+            BdkDescriptorType::Sh => DescriptorType::Sh,
+            BdkDescriptorType::Pkh => DescriptorType::Pkh,
+            BdkDescriptorType::Wpkh => DescriptorType::Wpkh,
+            BdkDescriptorType::Wsh => DescriptorType::Wsh,
+            BdkDescriptorType::ShWpkh => DescriptorType::ShWpkh,
+            BdkDescriptorType::ShWsh => DescriptorType::ShWsh,
+            BdkDescriptorType::ShWshSortedMulti => DescriptorType::ShWshSortedMulti,
+            BdkDescriptorType::ShSortedMulti => DescriptorType::ShSortedMulti,
+            BdkDescriptorType::WshSortedMulti => DescriptorType::WshSortedMulti,
+            BdkDescriptorType::Tr => DescriptorType::Tr,
+        }
+    }
+}
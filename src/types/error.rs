@@ -0,0 +1,361 @@
+use std::{any::Any, fmt};
+
+use bdk_wallet::{
+    bitcoin::{address, consensus::encode, psbt, FeeRate},
+    chain::local_chain::CannotConnectError,
+    coin_selection::InsufficientFunds,
+    descriptor::DescriptorError,
+    error::{BuildFeeBumpError, CreateTxError},
+    miniscript,
+    serde_json::{self, Map, Value},
+    signer::SignerError,
+    KeychainKind, LoadError, LoadMismatch,
+};
+use serde::Serialize;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+
+/// Machine-readable code of a [`BdkError`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The wallet cannot fund the transaction, see the `needed` and `available` amounts in sats.
+    InsufficientFunds = "insufficient_funds",
+    /// The output at `index` is below the dust limit.
+    OutputBelowDustLimit = "output_below_dust_limit",
+    /// The absolute fee is below the `required` fee in sats, when bumping a fee.
+    FeeTooLow = "fee_too_low",
+    /// The fee rate is below the `required` fee rate in sat/vB, when bumping a fee.
+    FeeRateTooLow = "fee_rate_too_low",
+    /// The transaction has no recipients and does not drain the wallet.
+    NoRecipients = "no_recipients",
+    /// No UTXO was selected to fund the transaction.
+    NoUtxosSelected = "no_utxos_selected",
+    /// An outpoint does not belong to the wallet, or its transaction is unknown.
+    UnknownUtxo = "unknown_utxo",
+    /// The previous transaction of the input spending `outpoint` is missing.
+    MissingNonWitnessUtxo = "missing_non_witness_utxo",
+    /// The descriptor of `keychain` has a spending policy that must be chosen.
+    SpendingPolicyRequired = "spending_policy_required",
+    /// The `requested` lock time is incompatible with the `required` one.
+    LockTime = "lock_time",
+    /// The transaction `txid` to bump is not in the wallet.
+    TransactionNotFound = "transaction_not_found",
+    /// The transaction `txid` to bump does not spend any output of the wallet.
+    NotOwnTransaction = "not_own_transaction",
+    /// The transaction `txid` to bump is already confirmed.
+    TransactionConfirmed = "transaction_confirmed",
+    /// The transaction `txid` to bump does not signal replaceability.
+    IrreplaceableTransaction = "irreplaceable_transaction",
+    /// The fee rate of the transaction to bump cannot be computed.
+    FeeRateUnavailable = "fee_rate_unavailable",
+    /// The loaded wallet does not match the `expected` network, genesis hash or descriptor of `keychain`,
+    /// see `kind`.
+    LoadMismatch = "load_mismatch",
+    /// The loaded changeset has no network.
+    MissingNetwork = "missing_network",
+    /// The loaded changeset has no genesis block.
+    MissingGenesis = "missing_genesis",
+    /// The loaded changeset has no descriptor for `keychain`.
+    MissingDescriptor = "missing_descriptor",
+    /// The persister already holds wallet data.
+    DataAlreadyExists = "data_already_exists",
+    /// The update cannot connect to the wallet's chain, the `try_include_height` block is missing.
+    CannotConnect = "cannot_connect",
+    /// A descriptor is invalid.
+    Descriptor = "descriptor",
+    /// A PSBT input cannot be signed.
+    Signer = "signer",
+    /// A PSBT is invalid or cannot be finalized.
+    Psbt = "psbt",
+    /// The Esplora server answered with HTTP `status` and `message`.
+    EsploraHttp = "esplora_http",
+    /// The Esplora request failed.
+    Esplora = "esplora",
+    /// The wallet persister failed.
+    Persister = "persister",
+    /// The browser storage failed.
+    Storage = "storage",
+    /// A backup cannot be decrypted, usually because of a wrong password.
+    DecryptionFailed = "decryption_failed",
+    /// The data has a `version` not supported by this release.
+    UnsupportedVersion = "unsupported_version",
+    /// The data cannot be parsed or decoded.
+    InvalidData = "invalid_data",
+    /// The JS coin selection callback failed.
+    CoinSelection = "coin_selection",
+    /// Any other error.
+    Unknown = "unknown",
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const BDK_ERROR: &'static str = r#"
+/**
+ * The error thrown by this library: a JS `Error` named `BdkError`, with a machine-readable `code` and
+ * the fields of the error, if any, in `data`.
+ */
+export interface BdkError extends Error {
+  name: "BdkError";
+  code: ErrorCode;
+  /** Integers beyond `Number.MAX_SAFE_INTEGER` are `bigint`s. */
+  data?: Record<string, unknown>;
+}
+"#;
+
+/// An error thrown by this library.
+///
+/// It is thrown to JS as an `Error` named `BdkError`, with a `code` property holding its
+/// [`ErrorCode`] and a `data` property holding its fields, if any.
+#[derive(Debug)]
+pub struct BdkError {
+    code: ErrorCode,
+    message: String,
+    data: Map<String, Value>,
+}
+
+impl BdkError {
+    pub fn new(code: ErrorCode, message: impl fmt::Display) -> Self {
+        BdkError {
+            code,
+            message: message.to_string(),
+            data: Map::new(),
+        }
+    }
+
+    /// Add the field `key` to the error data, unless `value` cannot be represented as JSON.
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.data.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for BdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl<E: std::error::Error + 'static> From<E> for BdkError {
+    fn from(error: E) -> Self {
+        classify(&error, error.to_string())
+    }
+}
+
+impl From<BdkError> for JsValue {
+    fn from(error: BdkError) -> Self {
+        let js_error = js_sys::Error::new(&error.message);
+        js_error.set_name("BdkError");
+
+        let set = |key: &str, value: JsValue| {
+            js_sys::Reflect::set(&js_error, &key.into(), &value).expect("Error is an object");
+        };
+        set("code", error.code.into());
+        if !error.data.is_empty() {
+            // A field that cannot be converted is left out rather than failing to throw the error.
+            let data = js_sys::Object::new();
+            for (key, value) in &error.data {
+                if let Some(value) = data_value(value) {
+                    let _ = js_sys::Reflect::set(&data, &key.into(), &value);
+                }
+            }
+            set("data", data.into());
+        }
+
+        js_error.into()
+    }
+}
+
+/// Convert an error field to JS, with integers beyond `Number.MAX_SAFE_INTEGER` as `bigint`s.
+fn data_value(value: &Value) -> Option<JsValue> {
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+    if let Value::Number(number) = value {
+        if let Some(n) = number.as_u64().filter(|n| *n > MAX_SAFE_INTEGER) {
+            return Some(js_sys::BigInt::from(n).into());
+        }
+        if let Some(n) = number.as_i64().filter(|n| n.unsigned_abs() > MAX_SAFE_INTEGER) {
+            return Some(js_sys::BigInt::from(n).into());
+        }
+    }
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible()).ok()
+}
+
+/// Map the known errors to their code and fields.
+fn classify(error: &(dyn Any + 'static), message: String) -> BdkError {
+    if let Some(error) = error.downcast_ref::<CreateTxError>() {
+        return create_tx_error(error);
+    }
+    if let Some(error) = error.downcast_ref::<InsufficientFunds>() {
+        return insufficient_funds(error);
+    }
+    if let Some(error) = error.downcast_ref::<BuildFeeBumpError>() {
+        return build_fee_bump_error(error);
+    }
+    if let Some(error) = error.downcast_ref::<LoadError>() {
+        return load_error(error);
+    }
+    if let Some(error) = error.downcast_ref::<CannotConnectError>() {
+        return BdkError::new(ErrorCode::CannotConnect, message).with("try_include_height", error.try_include_height);
+    }
+    #[cfg(feature = "esplora")]
+    if let Some(error) = esplora_error(error) {
+        return error;
+    }
+
+    let code = if error.is::<DescriptorError>()
+        || error.is::<miniscript::Error>()
+        || error.is::<miniscript::descriptor::ConversionError>()
+    {
+        ErrorCode::Descriptor
+    } else if error.is::<SignerError>() {
+        ErrorCode::Signer
+    } else if error.is::<psbt::Error>() || error.is::<psbt::ExtractTxError>() {
+        ErrorCode::Psbt
+    } else if error.is::<serde_json::Error>()
+        || error.is::<serde_wasm_bindgen::Error>()
+        || error.is::<ciborium::de::Error<std::io::Error>>()
+        || error.is::<encode::Error>()
+        || error.is::<encode::FromHexError>()
+        || error.is::<address::ParseError>()
+    {
+        ErrorCode::InvalidData
+    } else {
+        ErrorCode::Unknown
+    };
+    BdkError::new(code, message)
+}
+
+fn create_tx_error(error: &CreateTxError) -> BdkError {
+    match error {
+        CreateTxError::CoinSelection(error) => insufficient_funds(error),
+        CreateTxError::OutputBelowDustLimit(index) => {
+            BdkError::new(ErrorCode::OutputBelowDustLimit, error).with("index", index)
+        }
+        CreateTxError::FeeTooLow { required } => {
+            BdkError::new(ErrorCode::FeeTooLow, error).with("required", required.to_sat())
+        }
+        CreateTxError::FeeRateTooLow { required } => {
+            BdkError::new(ErrorCode::FeeRateTooLow, error).with("required", fee_rate(required))
+        }
+        CreateTxError::NoRecipients => BdkError::new(ErrorCode::NoRecipients, error),
+        CreateTxError::NoUtxosSelected => BdkError::new(ErrorCode::NoUtxosSelected, error),
+        CreateTxError::UnknownUtxo => BdkError::new(ErrorCode::UnknownUtxo, error),
+        CreateTxError::MissingNonWitnessUtxo(outpoint) => {
+            BdkError::new(ErrorCode::MissingNonWitnessUtxo, error).with("outpoint", outpoint.to_string())
+        }
+        CreateTxError::SpendingPolicyRequired(keychain) => {
+            BdkError::new(ErrorCode::SpendingPolicyRequired, error).with("keychain", keychain_name(*keychain))
+        }
+        CreateTxError::LockTime { requested, required } => BdkError::new(ErrorCode::LockTime, error)
+            .with("requested", requested.to_consensus_u32())
+            .with("required", required.to_consensus_u32()),
+        CreateTxError::Descriptor(_) => BdkError::new(ErrorCode::Descriptor, error),
+        CreateTxError::Psbt(_) | CreateTxError::MiniscriptPsbt(_) => BdkError::new(ErrorCode::Psbt, error),
+        _ => BdkError::new(ErrorCode::Unknown, error),
+    }
+}
+
+fn insufficient_funds(error: &InsufficientFunds) -> BdkError {
+    BdkError::new(ErrorCode::InsufficientFunds, error)
+        .with("needed", error.needed.to_sat())
+        .with("available", error.available.to_sat())
+}
+
+fn build_fee_bump_error(error: &BuildFeeBumpError) -> BdkError {
+    match error {
+        BuildFeeBumpError::UnknownUtxo(outpoint) => {
+            BdkError::new(ErrorCode::UnknownUtxo, error).with("outpoint", outpoint.to_string())
+        }
+        BuildFeeBumpError::TransactionNotFound(txid) => {
+            BdkError::new(ErrorCode::TransactionNotFound, error).with("txid", txid)
+        }
+        BuildFeeBumpError::TransactionConfirmed(txid) => {
+            BdkError::new(ErrorCode::TransactionConfirmed, error).with("txid", txid)
+        }
+        BuildFeeBumpError::IrreplaceableTransaction(txid) => {
+            BdkError::new(ErrorCode::IrreplaceableTransaction, error).with("txid", txid)
+        }
+        BuildFeeBumpError::FeeRateUnavailable => BdkError::new(ErrorCode::FeeRateUnavailable, error),
+    }
+}
+
+fn load_error(error: &LoadError) -> BdkError {
+    match error {
+        LoadError::Descriptor(_) => BdkError::new(ErrorCode::Descriptor, error),
+        LoadError::MissingNetwork => BdkError::new(ErrorCode::MissingNetwork, error),
+        LoadError::MissingGenesis => BdkError::new(ErrorCode::MissingGenesis, error),
+        LoadError::MissingDescriptor(keychain) => {
+            BdkError::new(ErrorCode::MissingDescriptor, error).with("keychain", keychain_name(*keychain))
+        }
+        LoadError::Mismatch(mismatch) => load_mismatch(mismatch),
+    }
+}
+
+fn load_mismatch(mismatch: &LoadMismatch) -> BdkError {
+    let (kind, keychain, loaded, expected) = match mismatch {
+        LoadMismatch::Network { loaded, expected } => ("network", None, loaded.to_string(), expected.to_string()),
+        LoadMismatch::Genesis { loaded, expected } => ("genesis hash", None, loaded.to_string(), expected.to_string()),
+        LoadMismatch::Descriptor {
+            keychain,
+            loaded,
+            expected,
+        } => {
+            let descriptor = |descriptor: &Option<_>| match descriptor {
+                Some(descriptor) => format!("{descriptor}"),
+                None => "none".to_string(),
+            };
+            let keychain = keychain_name(*keychain);
+            ("descriptor", Some(keychain), descriptor(loaded), descriptor(expected))
+        }
+    };
+
+    let message = match keychain {
+        Some(keychain) => format!("The {keychain} {kind} of the loaded wallet is {loaded}, expected {expected}"),
+        None => format!("The {kind} of the loaded wallet is {loaded}, expected {expected}"),
+    };
+    let error = BdkError::new(ErrorCode::LoadMismatch, message)
+        .with("kind", kind.replace(' ', "_"))
+        .with("loaded", loaded)
+        .with("expected", expected);
+    match keychain {
+        Some(keychain) => error.with("keychain", keychain),
+        None => error,
+    }
+}
+
+#[cfg(feature = "esplora")]
+fn esplora_error(error: &(dyn Any + 'static)) -> Option<BdkError> {
+    use bdk_esplora::esplora_client::Error;
+
+    let error = match error.downcast_ref::<Box<Error>>() {
+        Some(error) => error.as_ref(),
+        None => error.downcast_ref::<Error>()?,
+    };
+    Some(match error {
+        Error::HttpResponse { status, message } => BdkError::new(ErrorCode::EsploraHttp, error)
+            .with("status", status)
+            .with("message", message),
+        _ => BdkError::new(ErrorCode::Esplora, error),
+    })
+}
+
+fn keychain_name(keychain: KeychainKind) -> &'static str {
+    match keychain {
+        KeychainKind::External => "external",
+        KeychainKind::Internal => "internal",
+    }
+}
+
+/// A fee rate in sat/vB.
+fn fee_rate(fee_rate: &FeeRate) -> f64 {
+    fee_rate.to_sat_per_kwu() as f64 / 250.0
+}
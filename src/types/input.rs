@@ -1,7 +1,7 @@
 use std::ops::Deref;
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use bdk_wallet::bitcoin::TxIn as BdkTxIn;
+use bdk_wallet::bitcoin::{hex::DisplayHex, TxIn as BdkTxIn};
 
 use crate::types::{OutPoint, ScriptBuf};
 
@@ -36,6 +36,20 @@ impl TxIn {
         self.0.script_sig.clone().into()
     }
 
+    /// The sequence number, which suggests to miners which of two conflicting transactions should
+    /// be preferred, or 0xFFFFFFFF to ignore this feature. This is generally never used since the
+    /// miner behavior cannot be enforced.
+    #[wasm_bindgen(getter)]
+    pub fn sequence(&self) -> u32 {
+        self.0.sequence.to_consensus_u32()
+    }
+
+    /// The items of the witness stack, as hex strings.
+    #[wasm_bindgen(getter)]
+    pub fn witness(&self) -> Vec<String> {
+        self.0.witness.iter().map(|item| item.to_lower_hex_string()).collect()
+    }
+
     /// Returns the base size of this input.
     ///
     /// Base size excludes the witness data.
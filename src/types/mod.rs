@@ -5,12 +5,16 @@ mod block;
 mod chain;
 mod changeset;
 mod checkpoint;
+mod descriptor;
+mod error;
 mod fee;
 mod input;
 mod keychain;
 mod network;
 mod output;
+mod policy;
 mod psbt;
+mod sign_options;
 mod slip10;
 mod transaction;
 
@@ -21,11 +25,15 @@ pub use block::*;
 pub use chain::*;
 pub use changeset::*;
 pub use checkpoint::*;
+pub use descriptor::*;
+pub use error::*;
 pub use fee::*;
 pub use input::*;
 pub use keychain::*;
 pub use network::*;
 pub use output::*;
+pub use policy::*;
 pub use psbt::*;
+pub use sign_options::*;
 pub use slip10::*;
 pub use transaction::*;
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+
+use bdk_wallet::{descriptor::Policy as BdkPolicy, serde_json};
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, Serializer};
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+use crate::{result::JsResult, types::BdkError};
+
+#[wasm_bindgen(typescript_custom_section)]
+const POLICY: &'static str = r#"
+/**
+ * A key of a spending policy: a public key, an x-only public key, or the fingerprint of a key.
+ */
+export type PolicyKey = { pubkey: string } | { x_only_pubkey: string } | { fingerprint: string };
+
+/**
+ * Timelocks that must be satisfied to spend through a branch of a spending policy.
+ */
+export interface Condition {
+  /** The relative timelock, as a `nSequence` value. */
+  csv?: number;
+  /** The absolute timelock, as a `nLockTime` value. */
+  timelock?: number;
+}
+
+/**
+ * How much of a policy is satisfied. `items` are the indexes of the satisfied sub-policies, `n` the
+ * number of sub-policies and `m` the threshold. `conditions` holds the timelocks required by each
+ * satisfied sub-policy, keyed by its index as a string.
+ */
+export type Satisfaction =
+  | { type: "NONE" }
+  | { type: "COMPLETE"; condition: Condition }
+  | {
+      type: "PARTIAL" | "PARTIALCOMPLETE";
+      n: number;
+      m: number;
+      items: number[];
+      sorted?: boolean;
+      conditions?: Record<string, Condition[]>;
+    };
+
+/**
+ * A node of the spending policy tree of a descriptor, see `Wallet.policies`.
+ *
+ * `satisfaction` is what the PSBT, if any, already satisfies, and `contribution` what the signers of
+ * the wallet can satisfy.
+ */
+export type Policy = {
+  /** Identifier of the node, the key to select its sub-policies in `TxBuilder.policy_path`. */
+  id: string;
+  satisfaction: Satisfaction;
+  contribution: Satisfaction;
+} & (
+  | ({ type: "ECDSASIGNATURE" | "SCHNORRSIGNATURE" } & PolicyKey)
+  | { type: "SHA256PREIMAGE" | "HASH256PREIMAGE" | "RIPEMD160PREIMAGE" | "HASH160PREIMAGE"; hash: string }
+  | { type: "ABSOLUTETIMELOCK"; value: number }
+  | { type: "RELATIVETIMELOCK"; value: { Blocks: number } | { Time: number } }
+  | { type: "MULTISIG"; keys: PolicyKey[]; threshold: number }
+  | { type: "THRESH"; items: Policy[]; threshold: number }
+);
+
+/**
+ * The indexes of the sub-policies to satisfy, by policy node id, see `TxBuilder.policy_path`.
+ */
+export type PolicyPath = Record<string, number[]>;
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A spending policy tree, as a JS object. See the `Policy` TypeScript type.
+    #[wasm_bindgen(typescript_type = "Policy")]
+    pub type Policy;
+
+    /// The sub-policies to satisfy by policy node id, see the `PolicyPath` TypeScript type.
+    #[wasm_bindgen(typescript_type = "PolicyPath")]
+    pub type PolicyPath;
+}
+
+impl TryFrom<&BdkPolicy> for Policy {
+    type Error = BdkError;
+
+    fn try_from(policy: &BdkPolicy) -> JsResult<Self> {
+        // Going through JSON turns the integer keys of the satisfaction conditions into strings, which
+        // plain JS objects require.
+        let json = serde_json::to_value(policy)?;
+        Ok(json.serialize(&Serializer::json_compatible())?.unchecked_into())
+    }
+}
+
+impl PolicyPath {
+    pub(crate) fn to_map(&self) -> JsResult<BTreeMap<String, Vec<usize>>> {
+        Ok(from_value(JsValue::from(self))?)
+    }
+}
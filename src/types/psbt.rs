@@ -1,17 +1,29 @@
 use bdk_wallet::serde_json::to_string;
+use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
-use std::str::FromStr;
 
 use bdk_wallet::{
-    bitcoin::{Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf},
+    bitcoin::{
+        base64::{prelude::BASE64_STANDARD, Engine},
+        bip32::KeySource,
+        hex::{DisplayHex, FromHex},
+        psbt::{Input as BdkInput, Output as BdkOutput},
+        secp256k1::{Secp256k1, XOnlyPublicKey},
+        taproot::TapLeafHash,
+        Amount as BdkAmount, Psbt as BdkPsbt, ScriptBuf as BdkScriptBuf,
+    },
+    miniscript::psbt::{Error as MiniscriptPsbtError, PsbtExt},
     psbt::PsbtUtils,
 };
 
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::result::JsResult;
+use crate::{
+    bitcoin::{deserialize_psbt, serialize_psbt, set_psbt_version},
+    result::JsResult,
+};
 
-use super::{Address, Amount, FeeRate, Transaction};
+use super::{Address, Amount, BdkError, ErrorCode, FeeRate, ScriptBuf, Transaction, TxOut};
 
 /// A Partially Signed Transaction.
 #[wasm_bindgen]
@@ -58,16 +70,90 @@ impl Psbt {
         fee_rate.map(Into::into)
     }
 
+    /// The unsigned transaction, scriptSigs and witnesses for each input must be empty.
+    #[wasm_bindgen(getter)]
+    pub fn unsigned_tx(&self) -> Transaction {
+        self.0.unsigned_tx.clone().into()
+    }
+
+    /// The corresponding key-value map for each input in the unsigned transaction.
+    #[wasm_bindgen(getter)]
+    pub fn inputs(&self) -> Vec<PsbtInput> {
+        self.0.inputs.iter().cloned().map(Into::into).collect()
+    }
+
+    /// The corresponding key-value map for each output in the unsigned transaction.
+    #[wasm_bindgen(getter)]
+    pub fn outputs(&self) -> Vec<PsbtOutput> {
+        self.0.outputs.iter().cloned().map(Into::into).collect()
+    }
+
+    /// The PSBT version, either 0 or 2 (BIP370).
+    ///
+    /// Determines the format used when serializing the PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u32 {
+        self.0.version
+    }
+
+    /// Convert the PSBT to version 0 or 2 (BIP370).
+    ///
+    /// A PSBT converted to version 2 is flagged as modifiable, letting other participants add inputs
+    /// and outputs until it is signed. Converting to version 0 drops the fields specific to version 2.
+    pub fn set_version(&mut self, version: u32) -> JsResult<()> {
+        if version != 0 && version != 2 {
+            return Err(BdkError::new(
+                ErrorCode::UnsupportedVersion,
+                format!("Unsupported PSBT version {version}"),
+            )
+            .with("version", version));
+        }
+        set_psbt_version(&mut self.0, version);
+        Ok(())
+    }
+
     /// Serialize the PSBT to a string in base64 format
     #[allow(clippy::inherent_to_string)]
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
-        self.0.to_string()
+        BASE64_STANDARD.encode(self.to_bytes())
     }
 
-    /// Create a PSBT from a base64 string
+    /// Create a PSBT of version 0 or 2 from a base64 string
     pub fn from_string(val: &str) -> JsResult<Psbt> {
-        Ok(Psbt(BdkPsbt::from_str(val)?))
+        let bytes = BASE64_STANDARD.decode(val)?;
+        Psbt::from_bytes(&bytes)
+    }
+
+    /// Serialize the PSBT to its binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize_psbt(&self.0)
+    }
+
+    /// Create a PSBT of version 0 or 2 from its binary format
+    pub fn from_bytes(bytes: &[u8]) -> JsResult<Psbt> {
+        let psbt = deserialize_psbt(bytes).map_err(|e| BdkError::new(ErrorCode::Psbt, e))?;
+        Ok(Psbt(psbt))
+    }
+
+    /// Serialize the PSBT to a string in hex format
+    pub fn to_hex(&self) -> String {
+        self.to_bytes().to_lower_hex_string()
+    }
+
+    /// Create a PSBT of version 0 or 2 from a hex string
+    pub fn from_hex(val: &str) -> JsResult<Psbt> {
+        let bytes = Vec::<u8>::from_hex(val)?;
+        Psbt::from_bytes(&bytes)
+    }
+
+    /// Combines this PSBT with `other` (BIP 174).
+    ///
+    /// Used by multisig coordinators to merge the signatures and data sent back by each cosigner.
+    /// Fails if both PSBTs don't have the same unsigned transaction.
+    pub fn combine(&mut self, other: Psbt) -> JsResult<()> {
+        self.0.combine(other.into())?;
+        Ok(())
     }
 
     /// Serialize `Psbt` to JSON.
@@ -76,6 +162,34 @@ impl Psbt {
     }
 }
 
+impl Psbt {
+    /// Explains why each input that is not finalized could not be finalized.
+    pub(crate) fn finalize_errors(&self) -> Vec<InputFinalizeError> {
+        let secp = Secp256k1::verification_only();
+
+        self.0
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, input)| input.final_script_sig.is_none() && input.final_script_witness.is_none())
+            .map(|(index, _)| {
+                let reason = if self.0.get_utxo_for(index).is_none() {
+                    "Missing UTXO data (witness_utxo or non_witness_utxo)".to_string()
+                } else {
+                    // Run the generic miniscript finalizer on a copy to find out what is missing.
+                    match self.0.clone().finalize_inp_mut(&secp, index) {
+                        Err(MiniscriptPsbtError::InputError(e, _)) => e.to_string(),
+                        Err(e) => e.to_string(),
+                        Ok(()) => "Input could not be finalized".to_string(),
+                    }
+                };
+
+                InputFinalizeError { index, reason }
+            })
+            .collect()
+    }
+}
+
 impl From<BdkPsbt> for Psbt {
     fn from(inner: BdkPsbt) -> Self {
         Psbt(inner)
@@ -88,6 +202,360 @@ impl From<Psbt> for BdkPsbt {
     }
 }
 
+/// A key-value map for an input of the corresponding index in the unsigned transaction.
+#[wasm_bindgen]
+pub struct PsbtInput(BdkInput);
+
+impl Deref for PsbtInput {
+    type Target = BdkInput;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[wasm_bindgen]
+impl PsbtInput {
+    /// The non-witness transaction this input spends from. Should only be present for inputs
+    /// that spend non-segwit outputs or if it is unknown whether an input spends a segwit output.
+    #[wasm_bindgen(getter)]
+    pub fn non_witness_utxo(&self) -> Option<Transaction> {
+        self.0.non_witness_utxo.clone().map(Into::into)
+    }
+
+    /// The transaction output this input spends from. Should only be present for inputs which
+    /// spend segwit outputs, including P2SH embedded ones.
+    #[wasm_bindgen(getter)]
+    pub fn witness_utxo(&self) -> Option<TxOut> {
+        self.0.witness_utxo.clone().map(Into::into)
+    }
+
+    /// A map from public keys to their corresponding signature as would be pushed to the stack
+    /// from a scriptSig or witness for a non-taproot input.
+    #[wasm_bindgen(getter)]
+    pub fn partial_sigs(&self) -> Vec<PartialSig> {
+        self.0
+            .partial_sigs
+            .iter()
+            .map(|(pubkey, signature)| PartialSig {
+                pubkey: pubkey.to_string(),
+                signature: signature.to_vec().to_lower_hex_string(),
+            })
+            .collect()
+    }
+
+    /// The sighash type to be used for this input. Signatures for this input must use the sighash
+    /// type, finalizers must fail to finalize inputs which have signatures that do not match the
+    /// specified sighash type.
+    #[wasm_bindgen(getter)]
+    pub fn sighash_type(&self) -> Option<String> {
+        self.0.sighash_type.map(|sighash_type| sighash_type.to_string())
+    }
+
+    /// The redeem script for this input.
+    #[wasm_bindgen(getter)]
+    pub fn redeem_script(&self) -> Option<ScriptBuf> {
+        self.0.redeem_script.clone().map(Into::into)
+    }
+
+    /// The witness script for this input.
+    #[wasm_bindgen(getter)]
+    pub fn witness_script(&self) -> Option<ScriptBuf> {
+        self.0.witness_script.clone().map(Into::into)
+    }
+
+    /// A map from public keys needed to sign this input to their corresponding master key
+    /// fingerprints and derivation paths.
+    #[wasm_bindgen(getter)]
+    pub fn bip32_derivation(&self) -> Vec<Bip32Derivation> {
+        self.0
+            .bip32_derivation
+            .iter()
+            .map(|(pubkey, key_source)| Bip32Derivation::new(pubkey.to_string(), key_source))
+            .collect()
+    }
+
+    /// The finalized, fully-constructed scriptSig with signatures and any other scripts necessary
+    /// for this input to pass validation.
+    #[wasm_bindgen(getter)]
+    pub fn final_script_sig(&self) -> Option<ScriptBuf> {
+        self.0.final_script_sig.clone().map(Into::into)
+    }
+
+    /// The finalized, fully-constructed scriptWitness with signatures and any other scripts
+    /// necessary for this input to pass validation, as hex-encoded stack items.
+    #[wasm_bindgen(getter)]
+    pub fn final_script_witness(&self) -> Option<Vec<String>> {
+        self.0
+            .final_script_witness
+            .as_ref()
+            .map(|witness| witness.iter().map(|item| item.to_lower_hex_string()).collect())
+    }
+
+    /// Serialized taproot signature with sighash type for key spend, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_sig(&self) -> Option<String> {
+        self.0
+            .tap_key_sig
+            .map(|signature| signature.to_vec().to_lower_hex_string())
+    }
+
+    /// Map of taproot x-only keys to the leaves they are used in and their origin info.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_origins(&self) -> Vec<TapKeyOrigin> {
+        tap_key_origins(&self.0.tap_key_origins)
+    }
+
+    /// Taproot internal key, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn tap_internal_key(&self) -> Option<String> {
+        self.0.tap_internal_key.map(|key| key.to_string())
+    }
+
+    /// Whether the input has a finalized scriptSig or scriptWitness.
+    #[wasm_bindgen(getter)]
+    pub fn is_finalized(&self) -> bool {
+        self.0.final_script_sig.is_some() || self.0.final_script_witness.is_some()
+    }
+}
+
+impl From<BdkInput> for PsbtInput {
+    fn from(inner: BdkInput) -> Self {
+        PsbtInput(inner)
+    }
+}
+
+impl From<PsbtInput> for BdkInput {
+    fn from(input: PsbtInput) -> Self {
+        input.0
+    }
+}
+
+/// A key-value map for an output of the corresponding index in the unsigned transaction.
+#[wasm_bindgen]
+pub struct PsbtOutput(BdkOutput);
+
+impl Deref for PsbtOutput {
+    type Target = BdkOutput;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[wasm_bindgen]
+impl PsbtOutput {
+    /// The redeem script for this output.
+    #[wasm_bindgen(getter)]
+    pub fn redeem_script(&self) -> Option<ScriptBuf> {
+        self.0.redeem_script.clone().map(Into::into)
+    }
+
+    /// The witness script for this output.
+    #[wasm_bindgen(getter)]
+    pub fn witness_script(&self) -> Option<ScriptBuf> {
+        self.0.witness_script.clone().map(Into::into)
+    }
+
+    /// A map from public keys needed to spend this output to their corresponding master key
+    /// fingerprints and derivation paths.
+    #[wasm_bindgen(getter)]
+    pub fn bip32_derivation(&self) -> Vec<Bip32Derivation> {
+        self.0
+            .bip32_derivation
+            .iter()
+            .map(|(pubkey, key_source)| Bip32Derivation::new(pubkey.to_string(), key_source))
+            .collect()
+    }
+
+    /// The internal pubkey, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn tap_internal_key(&self) -> Option<String> {
+        self.0.tap_internal_key.map(|key| key.to_string())
+    }
+
+    /// Map of taproot x-only keys to the leaves they are used in and their origin info.
+    #[wasm_bindgen(getter)]
+    pub fn tap_key_origins(&self) -> Vec<TapKeyOrigin> {
+        tap_key_origins(&self.0.tap_key_origins)
+    }
+}
+
+impl From<BdkOutput> for PsbtOutput {
+    fn from(inner: BdkOutput) -> Self {
+        PsbtOutput(inner)
+    }
+}
+
+impl From<PsbtOutput> for BdkOutput {
+    fn from(output: PsbtOutput) -> Self {
+        output.0
+    }
+}
+
+/// An ECDSA signature of a [`PsbtInput`], along with its public key.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct PartialSig {
+    pubkey: String,
+    signature: String,
+}
+
+#[wasm_bindgen]
+impl PartialSig {
+    /// The public key, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn pubkey(&self) -> String {
+        self.pubkey.clone()
+    }
+
+    /// The signature with its sighash type, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn signature(&self) -> String {
+        self.signature.clone()
+    }
+}
+
+/// A public key along with the master key fingerprint and derivation path it was derived from.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Bip32Derivation {
+    pubkey: String,
+    fingerprint: String,
+    path: String,
+}
+
+impl Bip32Derivation {
+    fn new(pubkey: String, (fingerprint, path): &KeySource) -> Self {
+        Bip32Derivation {
+            pubkey,
+            fingerprint: fingerprint.to_string(),
+            path: path.to_string(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Bip32Derivation {
+    /// The public key, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn pubkey(&self) -> String {
+        self.pubkey.clone()
+    }
+
+    /// The fingerprint of the master key.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    /// The derivation path from the master key, e.g. `84'/1'/0'/0/0`.
+    #[wasm_bindgen(getter)]
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+}
+
+/// A taproot x-only public key along with the leaves it is used in and its origin.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct TapKeyOrigin {
+    pubkey: String,
+    leaf_hashes: Vec<String>,
+    fingerprint: String,
+    path: String,
+}
+
+#[wasm_bindgen]
+impl TapKeyOrigin {
+    /// The x-only public key, hex-encoded.
+    #[wasm_bindgen(getter)]
+    pub fn pubkey(&self) -> String {
+        self.pubkey.clone()
+    }
+
+    /// The hashes of the leaves the key is used in. Empty for the internal key.
+    #[wasm_bindgen(getter)]
+    pub fn leaf_hashes(&self) -> Vec<String> {
+        self.leaf_hashes.clone()
+    }
+
+    /// The fingerprint of the master key.
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.clone()
+    }
+
+    /// The derivation path from the master key, e.g. `86'/1'/0'/0/0`.
+    #[wasm_bindgen(getter)]
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+}
+
+fn tap_key_origins(origins: &BTreeMap<XOnlyPublicKey, (Vec<TapLeafHash>, KeySource)>) -> Vec<TapKeyOrigin> {
+    origins
+        .iter()
+        .map(|(pubkey, (leaf_hashes, (fingerprint, path)))| TapKeyOrigin {
+            pubkey: pubkey.to_string(),
+            leaf_hashes: leaf_hashes.iter().map(ToString::to_string).collect(),
+            fingerprint: fingerprint.to_string(),
+            path: path.to_string(),
+        })
+        .collect()
+}
+
+/// Result of finalizing a [`Psbt`].
+#[wasm_bindgen]
+pub struct FinalizeResult {
+    finalized: bool,
+    errors: Vec<InputFinalizeError>,
+}
+
+#[wasm_bindgen]
+impl FinalizeResult {
+    /// Whether all the inputs were finalized.
+    #[wasm_bindgen(getter)]
+    pub fn is_finalized(&self) -> bool {
+        self.finalized
+    }
+
+    /// The inputs that could not be finalized, with the reason.
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Vec<InputFinalizeError> {
+        self.errors.clone()
+    }
+}
+
+impl FinalizeResult {
+    pub(crate) fn new(finalized: bool, errors: Vec<InputFinalizeError>) -> Self {
+        FinalizeResult { finalized, errors }
+    }
+}
+
+/// An input of a [`Psbt`] that could not be finalized.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct InputFinalizeError {
+    index: usize,
+    reason: String,
+}
+
+#[wasm_bindgen]
+impl InputFinalizeError {
+    /// Index of the input in the PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Why the input could not be finalized.
+    #[wasm_bindgen(getter)]
+    pub fn reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
 /// A Transaction recipient
 #[wasm_bindgen]
 #[derive(Clone)]
@@ -0,0 +1,165 @@
+use std::{ops::Deref, str::FromStr};
+
+use bdk_wallet::{bitcoin::taproot::TapLeafHash, signer::TapLeavesOptions, SignOptions as BdkSignOptions};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::result::JsResult;
+
+/// Options for a software signer.
+///
+/// Adjust the behavior of our software signers and the way a transaction is finalized.
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct SignOptions(BdkSignOptions);
+
+impl Deref for SignOptions {
+    type Target = BdkSignOptions;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[wasm_bindgen]
+impl SignOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        SignOptions::default()
+    }
+
+    /// Whether the signer should trust the `witness_utxo`, if the `non_witness_utxo` hasn't been
+    /// provided
+    ///
+    /// Defaults to `false` to mitigate the "SegWit bug" which could trick the wallet into
+    /// paying a fee larger than expected.
+    ///
+    /// Some wallets, especially if relatively old, might not provide the `non_witness_utxo` for
+    /// SegWit transactions in the PSBT they generate: in those cases setting this to `true`
+    /// should correctly produce a signature, at the expense of an increased trust in the creator
+    /// of the PSBT.
+    #[wasm_bindgen(getter)]
+    pub fn trust_witness_utxo(&self) -> bool {
+        self.0.trust_witness_utxo
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_trust_witness_utxo(&mut self, trust_witness_utxo: bool) {
+        self.0.trust_witness_utxo = trust_witness_utxo;
+    }
+
+    /// Whether the wallet should assume a specific height has been reached when trying to finalize
+    /// a transaction
+    ///
+    /// The wallet will only "use" a timelock to satisfy the spending policy of an input if the
+    /// timelock height has already been reached. This option allows overriding the "current height" to let the
+    /// wallet use timelocks in the future to spend a coin.
+    #[wasm_bindgen(getter)]
+    pub fn assume_height(&self) -> Option<u32> {
+        self.0.assume_height
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_assume_height(&mut self, assume_height: Option<u32>) {
+        self.0.assume_height = assume_height;
+    }
+
+    /// Whether the signer should use the `sighash_type` set in the PSBT when signing, no matter
+    /// what its value is
+    ///
+    /// Defaults to `false` which will only allow signing using `SIGHASH_ALL`.
+    #[wasm_bindgen(getter)]
+    pub fn allow_all_sighashes(&self) -> bool {
+        self.0.allow_all_sighashes
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_allow_all_sighashes(&mut self, allow_all_sighashes: bool) {
+        self.0.allow_all_sighashes = allow_all_sighashes;
+    }
+
+    /// Whether to try finalizing the PSBT after the inputs are signed.
+    ///
+    /// Defaults to `true` which will try finalizing PSBT after inputs are signed.
+    #[wasm_bindgen(getter)]
+    pub fn try_finalize(&self) -> bool {
+        self.0.try_finalize
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_try_finalize(&mut self, try_finalize: bool) {
+        self.0.try_finalize = try_finalize;
+    }
+
+    /// Whether we should try to sign a taproot transaction with the taproot internal key
+    /// or not. This option is ignored if we're signing a non-taproot PSBT.
+    ///
+    /// Defaults to `true`, i.e., we always try to sign with the taproot internal key.
+    #[wasm_bindgen(getter)]
+    pub fn sign_with_tap_internal_key(&self) -> bool {
+        self.0.sign_with_tap_internal_key
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_sign_with_tap_internal_key(&mut self, sign_with_tap_internal_key: bool) {
+        self.0.sign_with_tap_internal_key = sign_with_tap_internal_key;
+    }
+
+    /// Whether we should grind ECDSA signature to ensure signing with low r
+    /// or not.
+    ///
+    /// Defaults to `true`, i.e., we always grind ECDSA signature to sign with low r.
+    #[wasm_bindgen(getter)]
+    pub fn allow_grinding(&self) -> bool {
+        self.0.allow_grinding
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_allow_grinding(&mut self, allow_grinding: bool) {
+        self.0.allow_grinding = allow_grinding;
+    }
+
+    /// Sign all the Taproot script-spend leaves the wallet has a key for (default).
+    pub fn sign_all_tap_leaves(&mut self) {
+        self.0.tap_leaves_options = TapLeavesOptions::All;
+    }
+
+    /// Only sign the given Taproot script-spend leaves, identified by their hex leaf hashes.
+    ///
+    /// Note that the signer could still ignore some of the specified leaves, if it doesn't have
+    /// the right key to sign them.
+    pub fn include_tap_leaves(&mut self, leaf_hashes: Vec<String>) -> JsResult<()> {
+        self.0.tap_leaves_options = TapLeavesOptions::Include(parse_leaf_hashes(leaf_hashes)?);
+        Ok(())
+    }
+
+    /// Sign all the Taproot script-spend leaves except the given ones, identified by their hex leaf hashes.
+    pub fn exclude_tap_leaves(&mut self, leaf_hashes: Vec<String>) -> JsResult<()> {
+        self.0.tap_leaves_options = TapLeavesOptions::Exclude(parse_leaf_hashes(leaf_hashes)?);
+        Ok(())
+    }
+
+    /// Don't sign any Taproot script-spend leaf.
+    pub fn sign_no_tap_leaves(&mut self) {
+        self.0.tap_leaves_options = TapLeavesOptions::None;
+    }
+}
+
+impl From<BdkSignOptions> for SignOptions {
+    fn from(inner: BdkSignOptions) -> Self {
+        SignOptions(inner)
+    }
+}
+
+impl From<SignOptions> for BdkSignOptions {
+    fn from(options: SignOptions) -> Self {
+        options.0
+    }
+}
+
+fn parse_leaf_hashes(leaf_hashes: Vec<String>) -> JsResult<Vec<TapLeafHash>> {
+    let mut hashes = Vec::with_capacity(leaf_hashes.len());
+    for leaf_hash in leaf_hashes {
+        hashes.push(TapLeafHash::from_str(&leaf_hash)?);
+    }
+    Ok(hashes)
+}
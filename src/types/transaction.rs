@@ -1,11 +1,20 @@
 use std::{ops::Deref, str::FromStr};
-use wasm_bindgen::prelude::wasm_bindgen;
 
-use bdk_wallet::bitcoin::{Transaction as BdkTransaction, Txid as BdkTxid};
+use bdk_wallet::{
+    bitcoin::{
+        consensus::encode::{deserialize, deserialize_hex, serialize, serialize_hex},
+        hex::DisplayHex,
+        Address as BdkAddress, Network as BdkNetwork, Script, Transaction as BdkTransaction, Txid as BdkTxid,
+    },
+    serde_json::to_string,
+};
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 
 use crate::result::JsResult;
 
-use super::{TxIn, TxOut};
+use super::{Network, TxIn, TxOut};
 
 /// Bitcoin transaction.
 ///
@@ -24,6 +33,52 @@ impl Deref for Transaction {
 
 #[wasm_bindgen]
 impl Transaction {
+    /// Deserialize a transaction from its raw consensus encoding.
+    pub fn from_bytes(bytes: &[u8]) -> JsResult<Transaction> {
+        let tx: BdkTransaction = deserialize(bytes)?;
+        Ok(tx.into())
+    }
+
+    /// Serialize the transaction with its raw consensus encoding, e.g. to broadcast it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serialize(&self.0)
+    }
+
+    /// Deserialize a transaction from the hex string of its raw consensus encoding.
+    pub fn from_hex(hex: &str) -> JsResult<Transaction> {
+        let tx: BdkTransaction = deserialize_hex(hex)?;
+        Ok(tx.into())
+    }
+
+    /// Serialize the transaction to the hex string of its raw consensus encoding.
+    pub fn to_hex(&self) -> String {
+        serialize_hex(&self.0)
+    }
+
+    /// The protocol version, is currently expected to be 1 or 2 (BIP 68).
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> i32 {
+        self.0.version.0
+    }
+
+    /// Block height or timestamp. Transaction cannot be included in a block until this height/time.
+    ///
+    /// Returned as the consensus encoded `nLockTime`: a block height if below 500,000,000, a UNIX
+    /// timestamp otherwise.
+    #[wasm_bindgen(getter)]
+    pub fn lock_time(&self) -> u32 {
+        self.0.lock_time.to_consensus_u32()
+    }
+
+    /// Returns the weight of this transaction, as defined by BIP-141.
+    ///
+    /// > Transaction weight is defined as Base transaction size * 3 + Total transaction size (ie.
+    /// > the same method as calculating Block weight from Base size and Total size).
+    #[wasm_bindgen(getter)]
+    pub fn weight(&self) -> u64 {
+        self.0.weight().to_wu()
+    }
+
     /// Returns the base transaction size.
     ///
     /// > Base transaction size is the size of the transaction serialised with the witness data stripped.
@@ -118,6 +173,25 @@ impl Transaction {
         Ok(output.into())
     }
 
+    /// Decode the transaction to a JS object, similar to Bitcoin Core's `decoderawtransaction`.
+    ///
+    /// Includes the txid and wtxid, the sizes, each input with its outpoint, sequence and witness,
+    /// and each output with its value, script ASM, script type and address on `network`.
+    /// Output values are `bigint`s so that any amount can be represented, unlike in
+    /// [`Transaction::decode_json`] where they are plain JSON numbers.
+    pub fn decode(&self, network: Network) -> JsResult<JsValue> {
+        let serializer = Serializer::new().serialize_large_number_types_as_bigints(true);
+        let decoded = DecodedTransaction::new(&self.0, network.into()).serialize(&serializer)?;
+        Ok(decoded)
+    }
+
+    /// Decode the transaction to a JSON string. See [`Transaction::decode`].
+    ///
+    /// Output values are plain JSON numbers, so `JSON.parse` loses precision above 2^53 satoshis.
+    pub fn decode_json(&self, network: Network) -> String {
+        to_string(&DecodedTransaction::new(&self.0, network.into())).expect("Serialization should not fail")
+    }
+
     #[wasm_bindgen(js_name = clone)]
     pub fn js_clone(&self) -> Transaction {
         self.clone()
@@ -173,3 +247,140 @@ impl From<Txid> for BdkTxid {
         txid.0
     }
 }
+
+/// A JSON-friendly view of a transaction, modeled after Bitcoin Core's `decoderawtransaction`.
+#[derive(Serialize)]
+struct DecodedTransaction {
+    txid: String,
+    wtxid: String,
+    version: i32,
+    size: u32,
+    vsize: u32,
+    weight: u32,
+    locktime: u32,
+    vin: Vec<DecodedInput>,
+    vout: Vec<DecodedOutput>,
+}
+
+#[derive(Serialize)]
+struct DecodedInput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coinbase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    txid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vout: Option<u32>,
+    #[serde(rename = "scriptSig", skip_serializing_if = "Option::is_none")]
+    script_sig: Option<DecodedScript>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    txinwitness: Vec<String>,
+    sequence: u32,
+}
+
+#[derive(Serialize)]
+struct DecodedOutput {
+    /// Value in satoshis.
+    value: u64,
+    n: u32,
+    #[serde(rename = "scriptPubKey")]
+    script_pubkey: DecodedScript,
+}
+
+#[derive(Serialize)]
+struct DecodedScript {
+    asm: String,
+    hex: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    script_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+}
+
+impl DecodedTransaction {
+    fn new(tx: &BdkTransaction, network: BdkNetwork) -> Self {
+        let is_coinbase = tx.is_coinbase();
+
+        DecodedTransaction {
+            txid: tx.compute_txid().to_string(),
+            wtxid: tx.compute_wtxid().to_string(),
+            version: tx.version.0,
+            size: tx.total_size() as u32,
+            vsize: tx.vsize() as u32,
+            weight: tx.weight().to_wu() as u32,
+            locktime: tx.lock_time.to_consensus_u32(),
+            vin: tx
+                .input
+                .iter()
+                .map(|txin| {
+                    let (outpoint, script_sig) = match is_coinbase {
+                        true => (None, None),
+                        false => (Some(txin.previous_output), Some(DecodedScript::new(&txin.script_sig))),
+                    };
+
+                    DecodedInput {
+                        coinbase: is_coinbase.then(|| txin.script_sig.to_hex_string()),
+                        txid: outpoint.map(|outpoint| outpoint.txid.to_string()),
+                        vout: outpoint.map(|outpoint| outpoint.vout),
+                        script_sig,
+                        txinwitness: txin.witness.iter().map(|item| item.to_lower_hex_string()).collect(),
+                        sequence: txin.sequence.to_consensus_u32(),
+                    }
+                })
+                .collect(),
+            vout: tx
+                .output
+                .iter()
+                .enumerate()
+                .map(|(n, txout)| {
+                    let mut script_pubkey = DecodedScript::new(&txout.script_pubkey);
+                    script_pubkey.script_type = Some(script_type(&txout.script_pubkey));
+                    script_pubkey.address = BdkAddress::from_script(&txout.script_pubkey, network)
+                        .ok()
+                        .map(|address| address.to_string());
+
+                    DecodedOutput {
+                        value: txout.value.to_sat(),
+                        n: n as u32,
+                        script_pubkey,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DecodedScript {
+    fn new(script: &Script) -> Self {
+        DecodedScript {
+            asm: script.to_asm_string(),
+            hex: script.to_hex_string(),
+            script_type: None,
+            address: None,
+        }
+    }
+}
+
+/// The type of an output script, with the names used by Bitcoin Core.
+fn script_type(script: &Script) -> &'static str {
+    if script.is_p2pk() {
+        "pubkey"
+    } else if script.is_p2pkh() {
+        "pubkeyhash"
+    } else if script.is_p2sh() {
+        "scripthash"
+    } else if script.is_multisig() {
+        "multisig"
+    } else if script.is_op_return() {
+        "nulldata"
+    } else if script.is_p2wpkh() {
+        "witness_v0_keyhash"
+    } else if script.is_p2wsh() {
+        "witness_v0_scripthash"
+    } else if script.is_p2tr() {
+        "witness_v1_taproot"
+    } else if script.is_witness_program() {
+        "witness_unknown"
+    } else {
+        "nonstandard"
+    }
+}
@@ -5,9 +5,9 @@ use bdk_wallet::{
     keys::ExtendedKey,
 };
 use serde_wasm_bindgen::from_value;
-use wasm_bindgen::prelude::{wasm_bindgen, JsError, JsValue};
+use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
 
-use crate::types::{AddressType, Network, SLIP10Node};
+use crate::types::{AddressType, BdkError, Descriptor, ErrorCode, Network, SLIP10Node};
 
 use super::result::JsResult;
 
@@ -28,6 +28,31 @@ impl DescriptorPair {
         DescriptorPair { external, internal }
     }
 
+    /// Split a BIP389 multipath descriptor, such as `wpkh([fingerprint/84'/0'/0']xpub/<0;1>/*)`, into
+    /// the external descriptor of its first path and the internal descriptor of its second path.
+    ///
+    /// Private keys are kept, so a descriptor with an `xprv` gives descriptors with an `xprv`.
+    pub fn from_multipath(descriptor: &str) -> JsResult<DescriptorPair> {
+        let descriptor = Descriptor::new(descriptor)?;
+        if !descriptor.is_multipath() {
+            return Err(BdkError::new(
+                ErrorCode::Descriptor,
+                "Descriptor is not multipath, expected keys with a <0;1> derivation step",
+            ));
+        }
+
+        match <[Descriptor; 2]>::try_from(descriptor.into_single_descriptors()?) {
+            Ok([external, internal]) => Ok(DescriptorPair::new(
+                external.to_string_with_secret(),
+                internal.to_string_with_secret(),
+            )),
+            Err(_) => Err(BdkError::new(
+                ErrorCode::Descriptor,
+                "Multipath keys must have exactly 2 paths, one per keychain",
+            )),
+        }
+    }
+
     #[wasm_bindgen(getter)]
     pub fn internal(&self) -> String {
         self.internal.clone()
@@ -42,7 +67,7 @@ impl DescriptorPair {
 #[wasm_bindgen]
 pub fn seed_to_descriptor(seed: &[u8], network: Network, address_type: AddressType) -> JsResult<DescriptorPair> {
     let (external, internal) = crate::bitcoin::seed_to_descriptor(seed, network.into(), address_type.into())
-        .map_err(|e| JsError::new(&e.to_string()))?;
+        .map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
 
     Ok(DescriptorPair::new(
         external.0.to_string_with_secret(&external.1),
@@ -62,7 +87,7 @@ pub fn xpriv_to_descriptor(
 
     let (external, internal) =
         crate::bitcoin::xpriv_to_descriptor(xprv, fingerprint, network.into(), address_type.into())
-            .map_err(|e| JsError::new(&e.to_string()))?;
+            .map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
 
     Ok(DescriptorPair::new(
         external.0.to_string_with_secret(&external.1),
@@ -82,14 +107,15 @@ pub fn xpub_to_descriptor(
 
     let (external, internal) =
         crate::bitcoin::xpub_to_descriptor(xpub, fingerprint, network.into(), address_type.into())
-            .map_err(|e| JsError::new(&e.to_string()))?;
+            .map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
 
     Ok(DescriptorPair::new(external.0.to_string(), internal.0.to_string()))
 }
 
 #[wasm_bindgen]
 pub fn seed_to_xpriv(seed: &[u8], network: Network) -> JsResult<String> {
-    let xprv = crate::bitcoin::seed_to_xpriv(seed, network.into()).map_err(|e| JsError::new(&e.to_string()))?;
+    let xprv =
+        crate::bitcoin::seed_to_xpriv(seed, network.into()).map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
 
     Ok(xprv.to_string())
 }
@@ -97,11 +123,82 @@ pub fn seed_to_xpriv(seed: &[u8], network: Network) -> JsResult<String> {
 #[wasm_bindgen]
 pub fn slip10_to_extended(slip10: JsValue, network: Network) -> JsResult<String> {
     let node: SLIP10Node = from_value(slip10.clone())?;
-    let extended_key =
-        crate::bitcoin::slip10_to_extended(node, network.into()).map_err(|e| JsError::new(&e.to_string()))?;
+    let extended_key = crate::bitcoin::slip10_to_extended(node, network.into())
+        .map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
 
     match &extended_key {
         ExtendedKey::Private(xprv) => Ok(xprv.0.to_string()),
         ExtendedKey::Public(xpub) => Ok(xpub.0.to_string()),
     }
 }
+
+/// Script context of a descriptor compiled from a policy, see [`compile_policy`].
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PolicyContext {
+    /// Pay to witness script hash.
+    Wsh = "wsh",
+    /// Pay to taproot.
+    Tr = "tr",
+}
+
+/// A descriptor compiled from a policy, see [`compile_policy`].
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct CompiledPolicy {
+    descriptor: String,
+    max_satisfaction_weight: u64,
+    satisfaction_weights: Vec<u64>,
+}
+
+#[wasm_bindgen]
+impl CompiledPolicy {
+    /// The descriptor, including its checksum.
+    #[wasm_bindgen(getter)]
+    pub fn descriptor(&self) -> String {
+        self.descriptor.clone()
+    }
+
+    /// The checksum of the descriptor.
+    #[wasm_bindgen(getter)]
+    pub fn checksum(&self) -> String {
+        self.descriptor
+            .rsplit_once('#')
+            .map(|(_, checksum)| checksum.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Worst-case weight of the witness satisfying the descriptor, in weight units.
+    #[wasm_bindgen(getter)]
+    pub fn max_satisfaction_weight(&self) -> u64 {
+        self.max_satisfaction_weight
+    }
+
+    /// Worst-case weight of the witness satisfying each taproot spending path, in weight units.
+    ///
+    /// For `tr`, the key path comes first unless the internal key is unspendable, followed by each leaf
+    /// of the script tree. For `wsh`, this is a single value, the worst case over all the branches of
+    /// the script, equal to `max_satisfaction_weight`.
+    #[wasm_bindgen(getter)]
+    pub fn satisfaction_weights(&self) -> Vec<u64> {
+        self.satisfaction_weights.clone()
+    }
+}
+
+/// Compile a Miniscript concrete policy, such as `or(pk(A),and(pk(B),older(4032)))`, into a descriptor.
+///
+/// Keys can be public keys or extended keys with an origin and a wildcard. For `tr`, the most
+/// probable key of the policy becomes the internal key.
+#[wasm_bindgen]
+pub fn compile_policy(policy: &str, context: PolicyContext) -> JsResult<CompiledPolicy> {
+    let descriptor = crate::bitcoin::compile_policy(policy, context == PolicyContext::Tr)
+        .map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
+    let weights =
+        crate::bitcoin::satisfaction_weights(&descriptor).map_err(|e| BdkError::new(ErrorCode::Descriptor, e))?;
+
+    Ok(CompiledPolicy {
+        descriptor: descriptor.to_string(),
+        max_satisfaction_weight: descriptor.max_weight_to_satisfy()?.to_wu(),
+        satisfaction_weights: weights.into_iter().map(|weight| weight.to_wu()).collect(),
+    })
+}
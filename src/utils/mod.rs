@@ -1,3 +1,4 @@
+mod bytes;
 mod descriptor;
 mod future;
 
@@ -5,6 +6,7 @@ mod future;
 mod panic_hook;
 pub mod result;
 
+pub(crate) use bytes::Bytes;
 pub use descriptor::*;
 pub use future::SendSyncWrapper;
 
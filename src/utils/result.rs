@@ -1,3 +1,3 @@
-use wasm_bindgen::JsError;
+use crate::types::BdkError;
 
-pub type JsResult<T> = Result<T, JsError>;
+pub type JsResult<T> = Result<T, BdkError>;
@@ -0,0 +1,145 @@
+//! Test suite for the compaction of a wallet's `ChangeSet`.
+
+use std::sync::Arc;
+
+use bdk_wallet::{
+    bitcoin::{
+        absolute, hashes::Hash, transaction, Amount, BlockHash, Network, OutPoint, ScriptBuf, Sequence, Transaction,
+        TxIn, TxOut, Txid, Witness,
+    },
+    chain::{BlockId, ConfirmationBlockTime},
+    ChangeSet as BdkChangeSet, KeychainKind, Wallet as BdkWallet,
+};
+use bitcoindevkit::types::ChangeSet;
+
+const EXTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p";
+const INTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/1/*)#dd6w3a4e";
+
+fn tx(previous_output: OutPoint, outputs: Vec<(u64, ScriptBuf)>) -> Transaction {
+    Transaction {
+        version: transaction::Version::TWO,
+        lock_time: absolute::LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            witness: Witness::from_slice(&[vec![0x30; 72], vec![0x02; 33]]),
+        }],
+        output: outputs
+            .into_iter()
+            .map(|(value, script_pubkey)| TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey,
+            })
+            .collect(),
+    }
+}
+
+fn insert_tx(changeset: &mut BdkChangeSet, tx: &Transaction, last_seen: u64) -> Txid {
+    let txid = tx.compute_txid();
+    changeset.tx_graph.txs.insert(Arc::new(tx.clone()));
+    changeset.tx_graph.last_seen.insert(txid, last_seen);
+    txid
+}
+
+fn load(changeset: BdkChangeSet) -> BdkWallet {
+    BdkWallet::load()
+        .descriptor(KeychainKind::External, Some(EXTERNAL_DESC))
+        .descriptor(KeychainKind::Internal, Some(INTERNAL_DESC))
+        .extract_keys()
+        .load_wallet_no_persist(changeset)
+        .expect("load")
+        .expect("wallet")
+}
+
+/// Balance and sorted txids of a wallet loaded from `changeset`.
+fn state(changeset: &BdkChangeSet) -> (u64, Vec<Txid>) {
+    let wallet = load(changeset.clone());
+    let mut txids: Vec<Txid> = wallet.transactions().map(|tx| tx.tx_node.txid).collect();
+    txids.sort();
+    (wallet.balance().total().to_sat(), txids)
+}
+
+#[test]
+fn test_compact_keeps_ancestors_of_kept_transactions() {
+    let mut wallet = BdkWallet::create(EXTERNAL_DESC, INTERNAL_DESC)
+        .network(Network::Testnet)
+        .create_wallet_no_persist()
+        .expect("wallet");
+    let mine: Vec<ScriptBuf> = (0..4)
+        .map(|_| wallet.reveal_next_address(KeychainKind::External).script_pubkey())
+        .collect();
+    let mut changeset = wallet.take_staged().expect("changeset");
+    let elsewhere = ScriptBuf::new_op_return([0x6a; 20]);
+
+    // A confirmed transaction funds the wallet with two outputs.
+    let funding = tx(
+        OutPoint::new(Txid::hash(b"funding"), 0),
+        vec![(50_000, mine[0].clone()), (20_000, mine[1].clone())],
+    );
+    let funding_txid = insert_tx(&mut changeset, &funding, 0);
+    let block_id = BlockId {
+        height: 100,
+        hash: BlockHash::hash(b"block"),
+    };
+    changeset.tx_graph.anchors.insert((
+        ConfirmationBlockTime {
+            block_id,
+            confirmation_time: 1_700_000_000,
+        },
+        funding_txid,
+    ));
+    changeset
+        .local_chain
+        .blocks
+        .insert(block_id.height, Some(block_id.hash));
+
+    // The first output is spent by a transaction seen long ago, which has a child seen recently, both
+    // replaced by a transaction seen after them.
+    let parent = tx(OutPoint::new(funding_txid, 0), vec![(40_000, mine[2].clone())]);
+    let parent_txid = insert_tx(&mut changeset, &parent, 1_000);
+    let replacement = tx(OutPoint::new(funding_txid, 0), vec![(45_000, elsewhere.clone())]);
+    insert_tx(&mut changeset, &replacement, 5_000);
+    let child = tx(OutPoint::new(parent_txid, 0), vec![(30_000, mine[3].clone())]);
+    let child_txid = insert_tx(&mut changeset, &child, 4_000);
+
+    // The second output is spent by a transaction replaced long ago, without descendants.
+    let replaced = tx(OutPoint::new(funding_txid, 1), vec![(15_000, mine[2].clone())]);
+    let replaced_txid = insert_tx(&mut changeset, &replaced, 1_000);
+    insert_tx(
+        &mut changeset,
+        &tx(OutPoint::new(funding_txid, 1), vec![(18_000, elsewhere)]),
+        2_000,
+    );
+
+    let before = state(&changeset);
+    let mut compacted = ChangeSet::from(changeset.clone());
+    compacted.compact(Some(3_000)).expect("compact");
+    let compacted = BdkChangeSet::from(compacted);
+    assert_eq!(state(&compacted), before);
+
+    let txids: Vec<Txid> = compacted.tx_graph.txs.iter().map(|tx| tx.compute_txid()).collect();
+    assert!(txids.contains(&parent_txid));
+    assert!(txids.contains(&child_txid));
+    assert!(!txids.contains(&replaced_txid));
+
+    // Compacting again doesn't change the wallet either.
+    let mut again = ChangeSet::from(compacted.clone());
+    again.compact(None).expect("compact");
+    assert_eq!(state(&again), before);
+}
+
+#[test]
+fn test_compact_without_genesis_leaves_changeset_unchanged() {
+    // A disconnected block is recorded without its hash, the genesis block is missing.
+    let mut changeset = BdkChangeSet::default();
+    changeset.local_chain.blocks.insert(100, None);
+    changeset
+        .local_chain
+        .blocks
+        .insert(101, Some(BlockHash::hash(b"block")));
+
+    let mut compacted = ChangeSet::from(changeset.clone());
+    assert!(compacted.compact(None).is_err());
+    assert_eq!(*compacted, changeset);
+}
@@ -3,8 +3,12 @@ import {
   Amount,
   EsploraClient,
   FeeRate,
+  LocalOutput,
   Network,
+  Psbt,
   Recipient,
+  SignOptions,
+  Txid,
   Wallet,
 } from "../../../pkg/bitcoindevkit";
 
@@ -25,6 +29,7 @@ describe("Esplora client", () => {
 
   let feeRate: FeeRate;
   let wallet: Wallet;
+  let sentTxid: string;
   const esploraClient = new EsploraClient(esploraUrl);
 
   it("creates a new wallet", () => {
@@ -76,6 +81,7 @@ describe("Esplora client", () => {
 
     const tx = psbt.extract_tx();
     const txid = tx.compute_txid();
+    sentTxid = txid.toString();
     await esploraClient.broadcast(tx);
 
     // Assert that we are aware of newly created addresses that were revealed during PSBT creation
@@ -93,6 +99,42 @@ describe("Esplora client", () => {
     expect(walletTx.chain_position.is_confirmed).toBe(false);
   }, 30000);
 
+  it("bumps the fee of an unconfirmed transaction", async () => {
+    const originalTx = wallet.get_tx(Txid.from_string(sentTxid)).tx;
+    const originalFeeRate = wallet.calculate_fee_rate(originalTx);
+    const bumpedFeeRate = new FeeRate(
+      originalFeeRate.to_sat_per_vb_ceil + BigInt(1)
+    );
+
+    const psbt = wallet
+      .build_fee_bump(Txid.from_string(sentTxid))
+      .fee_rate(bumpedFeeRate)
+      .finish();
+
+    expect(psbt.fee_rate().to_sat_per_vb_floor).toBeGreaterThan(
+      originalFeeRate.to_sat_per_vb_floor
+    );
+
+    const finalized = wallet.sign(psbt);
+    expect(finalized).toBeTruthy();
+
+    const tx = psbt.extract_tx();
+    expect(tx.compute_txid().toString()).not.toBe(sentTxid);
+    await esploraClient.broadcast(tx);
+
+    // A transaction received by the wallet cannot be bumped by it.
+    const received = wallet
+      .transactions()
+      .find(
+        (walletTx) =>
+          wallet.sent_and_received(walletTx.tx)[0].to_sat() === BigInt(0)
+      );
+    expect(received).toBeDefined();
+    expect(() => wallet.build_fee_bump(received!.txid)).toThrow(
+      expect.objectContaining({ code: "not_own_transaction" })
+    );
+  }, 30000);
+
   it("excludes utxos from a transaction", () => {
     const utxos = wallet.list_unspent();
     expect(utxos.length).toBeGreaterThan(0);
@@ -106,4 +148,380 @@ describe("Esplora client", () => {
         .finish();
     }).toThrow();
   });
+
+  it("spends only the manually selected utxos", () => {
+    const utxo = wallet.list_unspent()[0];
+    const psbt = wallet
+      .build_tx()
+      .add_utxo(utxo.outpoint)
+      .manually_selected_only()
+      .drain_to(wallet.peek_address("external", 0).address)
+      .finish();
+
+    wallet.sign(psbt);
+    const tx = psbt.extract_tx();
+    expect(tx.input.length).toBe(1);
+    expect(tx.input[0].previous_output.toString()).toBe(
+      utxo.outpoint.toString()
+    );
+  });
+
+  it("selects coins with the chosen algorithm", () => {
+    const largestUtxo = wallet
+      .list_unspent()
+      .reduce((a, b) =>
+        a.txout.value.to_sat() >= b.txout.value.to_sat() ? a : b
+      );
+    const psbt = wallet
+      .build_tx()
+      .coin_selection("largest_first")
+      .add_recipient(
+        new Recipient(
+          wallet.peek_address("external", 0).address,
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+
+    wallet.sign(psbt);
+    const tx = psbt.extract_tx();
+    expect(tx.input.length).toBe(1);
+    expect(tx.input[0].previous_output.toString()).toBe(
+      largestUtxo.outpoint.toString()
+    );
+
+    expect(() =>
+      wallet
+        .build_tx()
+        .long_term_fee_rate(new FeeRate(BigInt(10)))
+        .add_recipient(
+          new Recipient(
+            wallet.peek_address("external", 0).address,
+            Amount.from_sat(BigInt(1000))
+          )
+        )
+        .finish()
+    ).not.toThrow();
+
+    expect(() =>
+      wallet
+        .build_tx()
+        .coin_selection("largest_first")
+        .long_term_fee_rate(new FeeRate(BigInt(10)))
+        .add_recipient(
+          new Recipient(
+            wallet.peek_address("external", 0).address,
+            Amount.from_sat(BigInt(1000))
+          )
+        )
+        .finish()
+    ).toThrow(expect.objectContaining({ code: "coin_selection" }));
+  });
+
+  it("selects coins with a custom coin selection function", () => {
+    const recipient = () =>
+      new Recipient(
+        wallet.peek_address("external", 0).address,
+        Amount.from_sat(BigInt(1000))
+      );
+
+    let selected = "";
+    const psbt = wallet
+      .build_tx()
+      .coin_selection_fn((utxos: LocalOutput[]) => {
+        const largest = utxos.reduce((a, b) =>
+          a.txout.value.to_sat() >= b.txout.value.to_sat() ? a : b
+        );
+        selected = largest.outpoint.toString();
+        return [selected];
+      })
+      .add_recipient(recipient())
+      .finish();
+
+    wallet.sign(psbt);
+    const tx = psbt.extract_tx();
+    expect(tx.input.length).toBe(1);
+    expect(tx.input[0].previous_output.toString()).toBe(selected);
+
+    expect(() =>
+      wallet
+        .build_tx()
+        .coin_selection_fn(() => ["not an outpoint"])
+        .add_recipient(recipient())
+        .finish()
+    ).toThrow("Invalid outpoint");
+
+    expect(() =>
+      wallet
+        .build_tx()
+        .coin_selection_fn(() => [null, undefined])
+        .add_recipient(recipient())
+        .finish()
+    ).toThrow(expect.objectContaining({ code: "coin_selection" }));
+  });
+
+  it("finalizes a PSBT separately from signing", () => {
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          wallet.peek_address("external", 0).address,
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+
+    const signOptions = new SignOptions();
+    signOptions.try_finalize = false;
+    expect(wallet.sign(psbt, signOptions)).toBe(false);
+
+    const result = wallet.finalize_psbt(psbt);
+    expect(result.is_finalized).toBe(true);
+    expect(result.errors).toHaveLength(0);
+  });
+
+  it("signs with an external signer", async () => {
+    const watchOnly = Wallet.create(
+      network,
+      wallet.public_descriptor("external"),
+      wallet.public_descriptor("internal")
+    );
+    const fingerprint = wallet.public_descriptor("external").slice(5, 13);
+    let requests = 0;
+    watchOnly.add_signer(fingerprint, {
+      sign: async (psbt: Psbt) => {
+        requests += 1;
+        const signOptions = new SignOptions();
+        signOptions.try_finalize = false;
+        wallet.sign(psbt, signOptions);
+        return psbt;
+      },
+    });
+
+    const buildPsbt = () =>
+      wallet
+        .build_tx()
+        .add_recipient(
+          new Recipient(
+            wallet.peek_address("external", 0).address,
+            Amount.from_sat(BigInt(1000))
+          )
+        )
+        .finish();
+
+    const psbt = buildPsbt();
+    expect(watchOnly.sign(psbt)).toBe(false);
+    expect(requests).toBe(0);
+    expect(await watchOnly.sign_async(psbt)).toBe(true);
+    expect(requests).toBe(1);
+
+    watchOnly.add_signer(fingerprint, {
+      sign: async () => {
+        throw new Error("rejected on device");
+      },
+    });
+    await expect(watchOnly.sign_async(buildPsbt())).rejects.toThrow(
+      "rejected on device"
+    );
+
+    // The signatures requested before the wallet fails to sign are not kept for the next signing.
+    const unsigned = buildPsbt();
+    watchOnly.add_signer(fingerprint, {
+      sign: async () => {
+        const signed = Psbt.from_bytes(unsigned.to_bytes());
+        const signOptions = new SignOptions();
+        signOptions.try_finalize = false;
+        wallet.sign(signed, signOptions);
+        return signed;
+      },
+    });
+    // Insert a SIGHASH_NONE sighash type at the start of the first input map, after the global map
+    // holding only the unsigned transaction.
+    const tx = unsigned.unsigned_tx.to_bytes();
+    const inputsStart = 5 + 2 + (tx.length < 0xfd ? 1 : 3) + tx.length + 1;
+    const bytes = unsigned.to_bytes();
+    const sighashNone = Psbt.from_bytes(
+      new Uint8Array([
+        ...bytes.slice(0, inputsStart),
+        ...[0x01, 0x03, 0x04, 0x02, 0x00, 0x00, 0x00],
+        ...bytes.slice(inputsStart),
+      ])
+    );
+    await expect(watchOnly.sign_async(sighashNone)).rejects.toThrow();
+    expect(watchOnly.sign(Psbt.from_bytes(unsigned.to_bytes()))).toBe(false);
+  });
+
+  it("inspects the inputs and outputs of a PSBT", () => {
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          Address.from_string(
+            "tb1qd28npep0s8frcm3y7dxqajkcy2m40eysplyr9v",
+            network
+          ),
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+
+    const unsignedTx = psbt.unsigned_tx;
+    const inputs = psbt.inputs;
+    expect(inputs).toHaveLength(unsignedTx.input.length);
+    expect(psbt.outputs).toHaveLength(unsignedTx.output.length);
+
+    const input = inputs[0];
+    expect(input.witness_utxo).toBeDefined();
+    expect(input.partial_sigs).toHaveLength(0);
+    expect(input.is_finalized).toBe(false);
+    expect(input.bip32_derivation[0].path).toMatch(/^84'\/1'\/0'\//);
+
+    wallet.sign(psbt);
+    const signedInput = psbt.inputs[0];
+    expect(signedInput.is_finalized).toBe(true);
+    expect(signedInput.final_script_witness).toHaveLength(2);
+  });
+
+  it("serializes and combines PSBTs", () => {
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          wallet.peek_address("external", 0).address,
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+    const base64 = psbt.toString();
+
+    expect(Psbt.from_bytes(psbt.to_bytes()).toString()).toBe(base64);
+    expect(Psbt.from_hex(psbt.to_hex()).toString()).toBe(base64);
+    expect(() => Psbt.from_hex("not hex")).toThrow();
+
+    // A cosigner returns a signed copy of the PSBT
+    const cosigned = Psbt.from_string(base64);
+    const signOptions = new SignOptions();
+    signOptions.try_finalize = false;
+    wallet.sign(cosigned, signOptions);
+
+    psbt.combine(cosigned);
+    expect(psbt.inputs[0].partial_sigs).toHaveLength(1);
+  });
+
+  it("analyzes a PSBT before signing", () => {
+    const sendAmount = BigInt(1000);
+    const address = "tb1qd28npep0s8frcm3y7dxqajkcy2m40eysplyr9v";
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          Address.from_string(address, network),
+          Amount.from_sat(sendAmount)
+        )
+      )
+      .finish();
+
+    const analysis = wallet.analyze_psbt(psbt);
+    expect(analysis.anomalies).toHaveLength(0);
+    expect(analysis.inputs.every((input) => input.ownership === "ours")).toBe(
+      true
+    );
+
+    const recipients = analysis.outputs.filter(
+      (output) => output.kind === "recipient"
+    );
+    expect(recipients).toHaveLength(1);
+    expect(recipients[0].address.toString()).toBe(address);
+    expect(recipients[0].value.to_sat()).toBe(sendAmount);
+    expect(analysis.outputs.some((output) => output.kind === "change")).toBe(
+      true
+    );
+
+    expect(analysis.fee.to_sat()).toBe(psbt.fee().to_sat());
+    expect(analysis.net_sent).toBe(
+      sendAmount + analysis.fee.to_sat()
+    );
+
+    // A fee rate limit below the actual fee rate is flagged
+    const strict = wallet.analyze_psbt(psbt, new FeeRate(BigInt(0)));
+    expect(strict.anomalies.map((anomaly) => anomaly.kind)).toContain(
+      "absurd_fee"
+    );
+  });
+
+  it("flags inconsistent and invalid amounts in a PSBT", () => {
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          Address.from_string("tb1qd28npep0s8frcm3y7dxqajkcy2m40eysplyr9v", network),
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .finish();
+
+    const le = (value: bigint) => {
+      const bytes = new Uint8Array(8);
+      new DataView(bytes.buffer).setBigUint64(0, value, true);
+      return bytes;
+    };
+    const tamper = (bytes: Uint8Array, index: number, value: bigint) => {
+      expect(index).toBeGreaterThanOrEqual(0);
+      const tampered = bytes.slice();
+      tampered.set(le(value), index);
+      return Psbt.from_bytes(tampered);
+    };
+    const indexOf = (bytes: Uint8Array, value: bigint, last = false) => {
+      const hex = Buffer.from(bytes).toString("hex");
+      const needle = Buffer.from(le(value)).toString("hex");
+      const index = last ? hex.lastIndexOf(needle) : hex.indexOf(needle);
+      return index % 2 === 0 ? index / 2 : -1;
+    };
+    const bytes = psbt.to_bytes();
+    const kinds = (tampered: Psbt) =>
+      wallet.analyze_psbt(tampered).anomalies.map((anomaly) => anomaly.kind);
+
+    // The witness UTXO comes after the previous transaction of the input.
+    const value = psbt.inputs[0].witness_utxo.value.to_sat();
+    const lying = tamper(bytes, indexOf(bytes, value, true), value * BigInt(2));
+    expect(kinds(lying)).toContain("utxo_mismatch");
+
+    // The unsigned transaction comes first.
+    const invalid = tamper(bytes, indexOf(bytes, BigInt(1000)), BigInt(2) ** BigInt(62));
+    expect(kinds(invalid)).toContain("invalid_amount");
+    expect(wallet.analyze_psbt(invalid).fee).toBeUndefined();
+  });
+
+  it("creates, converts and signs PSBTv2", () => {
+    const psbt = wallet
+      .build_tx()
+      .add_recipient(
+        new Recipient(
+          wallet.peek_address("external", 0).address,
+          Amount.from_sat(BigInt(1000))
+        )
+      )
+      .psbt_version(2)
+      .finish();
+    expect(psbt.version).toBe(2);
+
+    const v2 = Psbt.from_string(psbt.toString());
+    expect(v2.version).toBe(2);
+    expect(v2.unsigned_tx.compute_txid().toString()).toBe(
+      psbt.unsigned_tx.compute_txid().toString()
+    );
+
+    // Convert back to the v0 format
+    v2.set_version(0);
+    expect(Psbt.from_string(v2.toString()).version).toBe(0);
+    expect(() => v2.set_version(1)).toThrow();
+
+    expect(wallet.sign(psbt)).toBe(true);
+    const signed = Psbt.from_bytes(psbt.to_bytes());
+    expect(signed.version).toBe(2);
+    expect(signed.inputs[0].is_finalized).toBe(true);
+    expect(signed.extract_tx().compute_txid().toString()).toBe(
+      psbt.unsigned_tx.compute_txid().toString()
+    );
+  });
 });
@@ -0,0 +1,74 @@
+import { Transaction } from "../../../pkg/bitcoindevkit";
+
+describe("Transaction", () => {
+  const rawTx =
+    "0200000000010111111111111111111111111111111111111111111111111111111111111111110100000000fdffffff0250c30000000000001600146a8f30e42f81d23c6e24f34c0ecad822b757e490e803000000000000076a0568656c6c6f02033044022102020202020202020202020202020202020202020202020202020202020202020240d10c00";
+
+  it("decodes a raw transaction", () => {
+    const tx = Transaction.from_hex(rawTx);
+
+    expect(tx.compute_txid().toString()).toBe(
+      "728189f8d743ab9dfcb045edf700b2e52f03b9c3d0ba659cc1cb40f70e726df6"
+    );
+    expect(tx.version).toBe(2);
+    expect(tx.lock_time).toBe(840000);
+    expect(tx.weight).toBe(BigInt(433));
+    expect(tx.vsize).toBe(109);
+
+    const input = tx.input[0];
+    expect(input.sequence).toBe(0xfffffffd);
+    expect(input.witness).toEqual(["304402", "02".repeat(33)]);
+    expect(tx.output[0].value.to_sat()).toBe(BigInt(50000));
+  });
+
+  it("encodes a raw transaction", () => {
+    const tx = Transaction.from_hex(rawTx);
+
+    expect(tx.to_hex()).toBe(rawTx);
+    expect(Transaction.from_bytes(tx.to_bytes()).to_hex()).toBe(rawTx);
+    expect(() => Transaction.from_hex("0200")).toThrow();
+  });
+
+  it("decodes a transaction to a structured view", () => {
+    const decoded = Transaction.from_hex(rawTx).decode("bitcoin");
+
+    expect(decoded.txid).toBe(
+      "728189f8d743ab9dfcb045edf700b2e52f03b9c3d0ba659cc1cb40f70e726df6"
+    );
+    expect(decoded.wtxid).toBe(
+      "19b9250bfcf9405b28c0eec0965eb33d2307744e51366a04805af640847f1e41"
+    );
+    expect(decoded.size).toBe(139);
+    expect(decoded.vsize).toBe(109);
+    expect(decoded.weight).toBe(433);
+    expect(decoded.locktime).toBe(840000);
+
+    expect(decoded.vin[0].txid).toBe("11".repeat(32));
+    expect(decoded.vin[0].vout).toBe(1);
+    expect(decoded.vin[0].txinwitness).toEqual(["304402", "02".repeat(33)]);
+
+    expect(decoded.vout[0].value).toBe(BigInt(50000));
+    expect(decoded.vout[0].scriptPubKey.type).toBe("witness_v0_keyhash");
+    expect(decoded.vout[0].scriptPubKey.address).toBe(
+      "bc1qd28npep0s8frcm3y7dxqajkcy2m40eystels7l"
+    );
+    expect(decoded.vout[1].scriptPubKey.type).toBe("nulldata");
+    expect(decoded.vout[1].scriptPubKey.asm).toBe(
+      "OP_RETURN OP_PUSHBYTES_5 68656c6c6f"
+    );
+    expect(decoded.vout[1].scriptPubKey.address).toBeUndefined();
+
+    const json = JSON.parse(
+      Transaction.from_hex(rawTx).decode_json("bitcoin")
+    );
+    expect(json.txid).toBe(decoded.txid);
+    expect(json.vout[0].value).toBe(50000);
+  });
+
+  it("decodes output values above the safe integer range", () => {
+    const largeValueTx = rawTx.replace("50c3000000000000", "ffffffffffffff7f");
+    const decoded = Transaction.from_hex(largeValueTx).decode("bitcoin");
+
+    expect(decoded.vout[0].value).toBe(BigInt("9223372036854775807"));
+  });
+});
@@ -1,5 +1,7 @@
 import {
   AddressType,
+  compile_policy,
+  Descriptor,
   Network,
   seed_to_descriptor,
   seed_to_xpriv,
@@ -71,4 +73,105 @@ describe("Utilities", () => {
       "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/1/*)#ltuly67e"
     );
   });
+
+  it("compiles a policy to a descriptor", () => {
+    const owner =
+      "[27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/0/*";
+    const heir =
+      "02e96fe52ef0e22d2f131dd425ce1893073a3c6ad20e8cac36726393dfb4856a4c";
+    const policy = `or(99@pk(${owner}),and(pk(${heir}),older(4032)))`;
+
+    const wsh = compile_policy(policy, "wsh");
+    expect(wsh.descriptor).toBe(
+      `wsh(or_d(pk(${owner}),and_v(v:pkh(${heir}),older(4032))))#c4jtglf0`
+    );
+    expect(wsh.checksum).toBe("c4jtglf0");
+    // The 67 bytes script with its length, plus the heir's branch, the worst case: an empty
+    // dissatisfaction of `pk(owner)` (1), a 72 bytes signature (73) and the heir's key (34).
+    expect(wsh.max_satisfaction_weight).toBe(BigInt(1 + 67 + 1 + 73 + 34));
+    expect(Array.from(wsh.satisfaction_weights)).toEqual([BigInt(176)]);
+
+    const tr = compile_policy(policy, "tr");
+    expect(tr.descriptor).toBe(
+      `tr(${owner},and_v(v:pk(${heir}),older(4032)))#3r7unfqv`
+    );
+    // The key path is a 65 bytes signature with its length. The leaf adds the 38 bytes script and
+    // the 33 bytes control block, each with its length.
+    expect(Array.from(tr.satisfaction_weights)).toEqual([
+      BigInt(1 + 65),
+      BigInt(1 + 65 + 1 + 38 + 1 + 33),
+    ]);
+
+    expect(() => compile_policy("or(pk(A)", "wsh")).toThrow();
+  });
+
+  it("parses and analyzes a descriptor", () => {
+    const descriptor = new Descriptor(
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)"
+    );
+    expect(descriptor.toString()).toBe(
+      "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/0/*)#wle7e0wp"
+    );
+    expect(descriptor.checksum).toBe("wle7e0wp");
+    expect(descriptor.descriptor_type).toBe("wpkh");
+    expect(descriptor.is_ranged).toBe(true);
+    expect(descriptor.is_multipath).toBe(false);
+    expect(descriptor.has_secrets).toBe(true);
+    expect(descriptor.to_string_with_secret()).toContain("tprv");
+    expect(descriptor.max_weight_to_satisfy()).toBe(BigInt(107));
+    expect(descriptor.address(0, network).toString()).toBe(
+      "tb1qjtgffm20l9vu6a7gacxvpu2ej4kdcsgc26xfdz"
+    );
+    expect(descriptor.script_pubkey(1).toString()).not.toBe(
+      descriptor.script_pubkey(0).toString()
+    );
+
+    const [key] = descriptor.keys;
+    expect(key.fingerprint).toBe("27f9035f");
+    expect(key.origin_path).toBe("84'/1'/0'");
+    expect(key.has_secret).toBe(true);
+
+    const multisig = new Descriptor(
+      "wsh(multi(2,[27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/<0;1>/*,02e96fe52ef0e22d2f131dd425ce1893073a3c6ad20e8cac36726393dfb4856a4c))"
+    );
+    expect(multisig.descriptor_type).toBe("wsh");
+    expect(multisig.is_multipath).toBe(true);
+    expect(multisig.has_secrets).toBe(false);
+    expect(multisig.keys.map((key) => key.origin_path)).toEqual([
+      "84'/1'/0'",
+      undefined,
+    ]);
+
+    const multipathPrivate = new Descriptor(
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/<0;1>/*)"
+    );
+    expect(multipathPrivate.toString()).toBe(
+      "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/<0;1>/*)#4a0a0acd"
+    );
+    expect(multipathPrivate.is_multipath).toBe(true);
+    expect(multipathPrivate.has_secrets).toBe(true);
+    expect(multipathPrivate.keys[0].has_secret).toBe(true);
+    expect(multipathPrivate.to_string_with_secret()).toContain(
+      "tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/<0;1>/*"
+    );
+    const [external, internal] = multipathPrivate.into_single_descriptors();
+    expect(external.is_multipath).toBe(false);
+    expect(external.to_string_with_secret()).toBe(
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/0/*)#uel0vg9p"
+    );
+    expect(internal.toString()).toBe(
+      "wpkh([27f9035f/84'/1'/0']tpubDCkv2fHDfPg5hB6bFqJ4fNiins2Z8r5vKtD4xq5irCG2HsUXkgHYsj3gfGTdvAv41hoJeXjfxu7EBQqZMm6SVkxztKFtaaE7HuLdkuL7KNq/1/*)#ltuly67e"
+    );
+    expect(internal.has_secrets).toBe(true);
+
+    // The paths of a private key can only differ after the hardened steps.
+    expect(
+      () =>
+        new Descriptor(
+          "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/<0';1'>/*)"
+        )
+    ).toThrow("same hardened derivation steps");
+
+    expect(() => new Descriptor("wpkh(not a key)")).toThrow();
+  });
 });
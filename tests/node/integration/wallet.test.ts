@@ -1,5 +1,16 @@
-import { Wallet } from "../../../pkg/bitcoindevkit";
-import type { Network } from "../../../pkg/bitcoindevkit";
+import {
+  Amount,
+  ChangeSet,
+  DescriptorPair,
+  Recipient,
+  SignOptions,
+  Wallet,
+} from "../../../pkg/bitcoindevkit";
+import type {
+  BdkError,
+  Network,
+  WalletPersister,
+} from "../../../pkg/bitcoindevkit";
 
 describe("Wallet", () => {
   const network: Network = "testnet";
@@ -30,6 +41,27 @@ describe("Wallet", () => {
     );
   });
 
+  it("creates a new wallet from a multipath descriptor", () => {
+    const multipathDesc =
+      "wpkh(tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU/84'/1'/0'/<0;1>/*)";
+
+    const descriptors = DescriptorPair.from_multipath(multipathDesc);
+    expect(descriptors.external).toBe(externalDesc);
+    expect(descriptors.internal).toBe(internalDesc);
+
+    const multipathWallet = Wallet.create_from_multipath(network, multipathDesc);
+    expect(
+      multipathWallet.peek_address("external", 0).address.toString()
+    ).toBe("tb1qjtgffm20l9vu6a7gacxvpu2ej4kdcsgc26xfdz");
+    expect(multipathWallet.public_descriptor("internal")).toBe(
+      wallet.public_descriptor("internal")
+    );
+
+    expect(() => DescriptorPair.from_multipath(externalDesc)).toThrow(
+      expect.objectContaining({ code: "descriptor" })
+    );
+  });
+
   it("loads a previously existing wallet", () => {
     const loadedWallet = Wallet.load(
       wallet.take_staged(),
@@ -42,4 +74,317 @@ describe("Wallet", () => {
       loadedWallet.next_unused_address("external").address.toString()
     ).toBe("tb1qjtgffm20l9vu6a7gacxvpu2ej4kdcsgc26xfdz");
   });
+
+  it("checks the network, genesis hash and descriptors when loading", () => {
+    const changeset = Wallet.create(
+      network,
+      externalDesc,
+      internalDesc
+    ).take_staged();
+    const testnetGenesis =
+      "000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943";
+
+    const loaded = Wallet.load(
+      ChangeSet.from_json(changeset.to_json()),
+      externalDesc,
+      internalDesc,
+      network,
+      testnetGenesis
+    );
+    expect(loaded.network).toBe(network);
+
+    expect(() =>
+      Wallet.load(
+        ChangeSet.from_json(changeset.to_json()),
+        undefined,
+        undefined,
+        "signet"
+      )
+    ).toThrow("The network of the loaded wallet is testnet, expected signet");
+    expect(() =>
+      Wallet.load(
+        ChangeSet.from_json(changeset.to_json()),
+        undefined,
+        undefined,
+        undefined,
+        testnetGenesis.replace(/3$/, "2")
+      )
+    ).toThrow("The genesis hash of the loaded wallet");
+    expect(() =>
+      Wallet.load(
+        ChangeSet.from_json(changeset.to_json()),
+        externalDesc,
+        externalDesc
+      )
+    ).toThrow("The internal descriptor of the loaded wallet");
+  });
+
+  it("encodes a changeset to bytes", () => {
+    const changeset = Wallet.create(
+      network,
+      externalDesc,
+      internalDesc
+    ).take_staged();
+    const json = changeset.to_json();
+
+    const bytes = changeset.to_bytes();
+    expect(bytes[0]).toBe(1);
+    expect(bytes.length).toBeLessThan(json.length);
+    expect(ChangeSet.from_bytes(bytes).to_json()).toBe(json);
+
+    expect(() => ChangeSet.from_bytes(new Uint8Array([2]))).toThrow(
+      "Unsupported ChangeSet encoding version"
+    );
+    expect(() => ChangeSet.from_bytes(new Uint8Array([]))).toThrow();
+  });
+
+  it("migrates changesets from older schema versions", () => {
+    const json = Wallet.create(
+      network,
+      externalDesc,
+      internalDesc
+    ).take_staged().to_json();
+    const envelope = JSON.parse(json);
+    expect(envelope.version).toBe(1);
+
+    // Changesets persisted before the envelope was introduced
+    const legacy = JSON.stringify(envelope.changeset);
+    expect(ChangeSet.from_json(legacy).to_json()).toBe(json);
+    expect(ChangeSet.from_js(envelope.changeset).to_json()).toBe(json);
+    expect(ChangeSet.from_js(envelope).to_json()).toBe(json);
+    expect(ChangeSet.from_js(ChangeSet.from_json(json).to_js()).to_json()).toBe(
+      json
+    );
+
+    const future = JSON.stringify({ ...envelope, version: 99 });
+    expect(() => ChangeSet.from_json(future)).toThrow(
+      "ChangeSet schema version 99 is not supported"
+    );
+  });
+
+  it("compacts a merged changeset", () => {
+    const fresh = Wallet.create(network, externalDesc, internalDesc);
+    const changeset = fresh.take_staged();
+    fresh.reveal_next_address("external");
+    const revealed = fresh.take_staged();
+    expect(() => revealed.compact()).toThrow("without the genesis block");
+
+    changeset.merge(revealed);
+    const json = changeset.to_json();
+    changeset.compact(Math.floor(Date.now() / 1000));
+    expect(changeset.to_json()).toBe(json);
+
+    const loaded = Wallet.load(changeset, externalDesc, internalDesc);
+    expect(loaded.derivation_index("external")).toBe(0);
+  });
+
+  it("exports and loads an encrypted backup", () => {
+    const backup = wallet.export_encrypted_backup("correct horse");
+    expect(Buffer.from(backup).toString("latin1")).not.toContain("tprv");
+
+    const restored = Wallet.load_encrypted(backup, "correct horse");
+    expect(restored.network).toBe(network);
+    expect(restored.derivation_index("external")).toBe(
+      wallet.derivation_index("external")
+    );
+    expect(restored.public_descriptor("external")).toBe(
+      wallet.public_descriptor("external")
+    );
+
+    expect(() => Wallet.load_encrypted(backup, "wrong password")).toThrow(
+      "check the password"
+    );
+
+    // Version, then the memory, iterations and parallelism costs as little-endian u32s.
+    const withCost = (index: number, cost: number) => {
+      const tampered = backup.slice();
+      new DataView(tampered.buffer).setUint32(1 + 4 * index, cost, true);
+      return tampered;
+    };
+    expect(() =>
+      Wallet.load_encrypted(withCost(1, 0xffffffff), "correct horse")
+    ).toThrow("iterations cost too high");
+    expect(() =>
+      Wallet.load_encrypted(withCost(2, 0xffffff), "correct horse")
+    ).toThrow("parallelism cost too high");
+  });
+
+  it("throws typed errors", () => {
+    const thrown = (f: () => unknown): BdkError => {
+      try {
+        f();
+      } catch (e) {
+        return e as BdkError;
+      }
+      throw new Error("expected an error");
+    };
+
+    const mismatch = thrown(() =>
+      Wallet.load(
+        Wallet.create(network, externalDesc, internalDesc).take_staged(),
+        internalDesc,
+        internalDesc
+      )
+    );
+    expect(mismatch).toBeInstanceOf(Error);
+    expect(mismatch.name).toBe("BdkError");
+    expect(mismatch.code).toBe("load_mismatch");
+    expect(mismatch.data).toMatchObject({
+      kind: "descriptor",
+      keychain: "external",
+    });
+
+    const address = wallet.peek_address("external", 0).address;
+    const insufficient = thrown(() =>
+      wallet
+        .build_tx()
+        .add_recipient(
+          new Recipient(address, Amount.from_sat(BigInt(10_000)))
+        )
+        .finish()
+    );
+    expect(insufficient.code).toBe("insufficient_funds");
+    expect(insufficient.data).toMatchObject({ available: 0 });
+    expect(insufficient.data?.needed).toBeGreaterThan(10_000);
+
+    const huge = thrown(() =>
+      wallet
+        .build_tx()
+        .add_recipient(
+          new Recipient(address, Amount.from_sat(BigInt(2) ** BigInt(60)))
+        )
+        .finish()
+    );
+    expect(huge.code).toBe("insufficient_funds");
+    expect(typeof huge.data?.needed).toBe("bigint");
+  });
+
+  it("inspects spending policies and chooses a policy path", () => {
+    const tprv =
+      "tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU";
+    const descriptor = (change: number) =>
+      `wsh(or_d(pk(${tprv}/84'/1'/0'/${change}/*),and_v(v:pk(${tprv}/84'/1'/1'/${change}/*),older(144))))`;
+    const timelocked = Wallet.create(network, descriptor(0), descriptor(1));
+
+    const policy = timelocked.policies("external");
+    expect(policy).toMatchObject({ type: "THRESH", threshold: 1 });
+    if (policy?.type !== "THRESH") throw new Error("expected a THRESH policy");
+    expect(policy.items).toHaveLength(2);
+    expect(policy.items[1]).toMatchObject({
+      type: "THRESH",
+      threshold: 2,
+      contribution: { type: "PARTIALCOMPLETE" },
+    });
+    expect(wallet.policies("external")).toMatchObject({
+      type: "ECDSASIGNATURE",
+    });
+
+    const buildTx = () =>
+      timelocked
+        .build_tx()
+        .add_recipient(
+          new Recipient(
+            timelocked.peek_address("external", 0).address,
+            Amount.from_sat(BigInt(10_000))
+          )
+        );
+    expect(() => buildTx().finish()).toThrow("Spending policy required");
+
+    const internalPolicy = timelocked.policies("internal");
+    expect(() =>
+      buildTx()
+        .policy_path({ [policy.id]: [0] }, "external")
+        .policy_path({ [internalPolicy!.id]: [0] }, "internal")
+        .finish()
+    ).toThrow("Insufficient funds");
+  });
+
+  it("inspects the policy of a partially signing multisig wallet", () => {
+    const tprv =
+      "tprv8ZgxMBicQKsPf6vydw7ixvsLKY79hmeXujBkGCNCApyft92yVYng2y28JpFZcneBYTTHycWSRpokhHE25GfHPBxnW5GpSm2dMWzEi9xxEyU";
+    const tpub =
+      "tpubD6NzVbkrYhZ4Y529GvCkRKDNJ6AAF8VptYbpg3GSbqTkUQnNi3cYTzzDtjPqfcoZdii14nQRPLt4A9LCHGUUzL6RC3z1ZPUdP1yCaAwR3nZ";
+    const descriptor = (change: number) =>
+      `wsh(multi(2,${tprv}/84'/1'/0'/${change}/*,${tpub}/84/1/1/${change}/*))`;
+    const cosigner = Wallet.create(network, descriptor(0), descriptor(1));
+
+    // The wallet holds one of the two keys, the conditions are keyed by the index of the key.
+    expect(cosigner.policies("external")).toMatchObject({
+      type: "MULTISIG",
+      threshold: 2,
+      contribution: {
+        type: "PARTIAL",
+        n: 2,
+        m: 2,
+        items: [0],
+        conditions: { "0": [{}] },
+      },
+    });
+
+    const watchOnly = Wallet.create(
+      network,
+      cosigner.public_descriptor("external"),
+      cosigner.public_descriptor("internal")
+    );
+    expect(watchOnly.policies("external")?.contribution).toMatchObject({
+      type: "PARTIAL",
+      items: [],
+    });
+  });
+
+  it("configures sign options", () => {
+    const options = new SignOptions();
+    expect(options.trust_witness_utxo).toBe(false);
+    expect(options.try_finalize).toBe(true);
+    expect(options.assume_height).toBeUndefined();
+
+    options.trust_witness_utxo = true;
+    options.try_finalize = false;
+    options.assume_height = 840000;
+    expect(options.trust_witness_utxo).toBe(true);
+    expect(options.try_finalize).toBe(false);
+    expect(options.assume_height).toBe(840000);
+
+    expect(() => options.include_tap_leaves(["not a leaf hash"])).toThrow();
+  });
+
+  it("persists changes with a persister", async () => {
+    let stored: ChangeSet | undefined;
+    const persister: WalletPersister = {
+      // Return a copy, the wallet takes ownership of the changeset
+      initialize: async () => stored && ChangeSet.from_json(stored.to_json()),
+      persist: async (changeset) => {
+        if (stored) {
+          stored.merge(changeset);
+        } else {
+          stored = changeset;
+        }
+      },
+    };
+
+    expect(await Wallet.load_persisted(persister)).toBeUndefined();
+
+    const persisted = await Wallet.create_persisted(
+      network,
+      externalDesc,
+      internalDesc,
+      persister
+    );
+    expect(stored).toBeDefined();
+    await expect(
+      Wallet.create_persisted(network, externalDesc, internalDesc, persister)
+    ).rejects.toThrow("already has wallet data");
+
+    const address = persisted.reveal_next_address("external");
+    await persisted.persist();
+
+    const loaded = await Wallet.load_persisted(
+      persister,
+      externalDesc,
+      internalDesc
+    );
+    expect(loaded.derivation_index("external")).toBe(address.index);
+
+    await expect(wallet.persist()).rejects.toThrow("no persister");
+  });
 });
@@ -0,0 +1,307 @@
+//! Test suite for the PSBT version 2 encoding, following the cases of the BIP370 test vectors.
+
+use bdk_wallet::bitcoin::{
+    absolute::LockTime,
+    ecdsa,
+    hashes::Hash,
+    psbt::raw,
+    secp256k1::{self, Secp256k1, SecretKey},
+    transaction::Version,
+    Amount, EcdsaSighashType, OutPoint, Psbt, PublicKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
+};
+use bitcoindevkit::bitcoin::{deserialize_psbt, serialize_psbt, set_psbt_version};
+
+const TXID: [u8; 32] = [0x0b; 32];
+const SCRIPT: [u8; 22] = [
+    0x00, 0x14, 0xd8, 0x5c, 0x2b, 0x71, 0xd0, 0x06, 0x0b, 0x09, 0xc9, 0x88, 0x6a, 0xeb, 0x81, 0x5e, 0x50, 0x99, 0x1d,
+    0xda, 0x12, 0x4d,
+];
+
+fn pair(key_type: u8, value: &[u8]) -> (u8, Vec<u8>) {
+    (key_type, value.to_vec())
+}
+
+/// Serialize a PSBT from its maps of key types without key data, sorted like `serialize_psbt`.
+fn encode(maps: Vec<Vec<(u8, Vec<u8>)>>) -> Vec<u8> {
+    let mut bytes = b"psbt\xff".to_vec();
+    for mut map in maps {
+        map.sort();
+        for (key_type, value) in map {
+            bytes.extend([1, key_type, value.len() as u8]);
+            bytes.extend(value);
+        }
+        bytes.push(0x00);
+    }
+    bytes
+}
+
+fn global(input_count: u8) -> Vec<(u8, Vec<u8>)> {
+    vec![
+        pair(0x02, &2u32.to_le_bytes()),
+        pair(0x04, &[input_count]),
+        pair(0x05, &[2]),
+        pair(0xfb, &2u32.to_le_bytes()),
+    ]
+}
+
+fn input(vout: u32) -> Vec<(u8, Vec<u8>)> {
+    vec![pair(0x0e, &TXID), pair(0x0f, &vout.to_le_bytes())]
+}
+
+fn output(amount: i64) -> Vec<(u8, Vec<u8>)> {
+    vec![pair(0x03, &amount.to_le_bytes()), pair(0x04, &SCRIPT)]
+}
+
+/// A 1 input, 2 output PSBTv2, with the extra global and input fields.
+fn psbt_v2(global_fields: Vec<(u8, Vec<u8>)>, input_fields: Vec<(u8, Vec<u8>)>) -> Vec<u8> {
+    encode(vec![
+        [global(1), global_fields].concat(),
+        [input(0), input_fields].concat(),
+        output(99_999_699),
+        output(100_000_000),
+    ])
+}
+
+fn assert_round_trip(bytes: &[u8]) -> Psbt {
+    let psbt = deserialize_psbt(bytes).expect("valid PSBTv2");
+    assert_eq!(psbt.version, 2);
+    assert_eq!(serialize_psbt(&psbt), bytes);
+    psbt
+}
+
+fn unsigned_tx() -> Transaction {
+    Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::from_height(800_000).unwrap(),
+        input: vec![TxIn {
+            previous_output: OutPoint::new(Txid::from_byte_array(TXID), 0),
+            sequence: Sequence::ENABLE_RBF_NO_LOCKTIME,
+            ..Default::default()
+        }],
+        output: vec![TxOut {
+            value: Amount::from_sat(1_000),
+            script_pubkey: ScriptBuf::from_bytes(SCRIPT.to_vec()),
+        }],
+    }
+}
+
+fn modifiable_flags(psbt: &Psbt) -> Option<u8> {
+    let key = raw::Key {
+        type_value: 0x06,
+        key: vec![],
+    };
+    psbt.unknown.get(&key).map(|flags| flags[0])
+}
+
+fn sign(psbt: &mut Psbt, sighash_type: EcdsaSighashType) {
+    let secp = Secp256k1::new();
+    let key = PublicKey::new(SecretKey::from_slice(&[1; 32]).unwrap().public_key(&secp));
+    let signature = ecdsa::Signature {
+        signature: secp256k1::ecdsa::Signature::from_compact(&[1; 64]).unwrap(),
+        sighash_type,
+    };
+    psbt.inputs[0].partial_sigs.insert(key, signature);
+}
+
+#[test]
+fn test_required_fields_only() {
+    let psbt = assert_round_trip(&psbt_v2(vec![], vec![]));
+
+    let tx = &psbt.unsigned_tx;
+    assert_eq!(tx.version, Version::TWO);
+    assert_eq!(tx.lock_time, LockTime::ZERO);
+    assert_eq!(
+        tx.input[0].previous_output,
+        OutPoint::new(Txid::from_byte_array(TXID), 0)
+    );
+    assert_eq!(tx.input[0].sequence, Sequence::MAX);
+    assert_eq!(tx.output[0].value, Amount::from_sat(99_999_699));
+    assert_eq!(tx.output[1].script_pubkey.as_bytes(), SCRIPT);
+}
+
+#[test]
+fn test_sequence() {
+    let psbt = assert_round_trip(&psbt_v2(vec![], vec![pair(0x10, &0xfffffffeu32.to_le_bytes())]));
+    assert_eq!(psbt.unsigned_tx.input[0].sequence, Sequence::ENABLE_LOCKTIME_NO_RBF);
+
+    // An explicit final sequence is kept too.
+    assert_round_trip(&psbt_v2(vec![], vec![pair(0x10, &u32::MAX.to_le_bytes())]));
+}
+
+#[test]
+fn test_required_locktimes() {
+    let time = pair(0x11, &1_657_048_460u32.to_le_bytes());
+    let height = pair(0x12, &10_000u32.to_le_bytes());
+
+    let psbt = assert_round_trip(&psbt_v2(vec![], vec![time.clone()]));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_time(1_657_048_460).unwrap());
+
+    let psbt = assert_round_trip(&psbt_v2(vec![], vec![height.clone()]));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_height(10_000).unwrap());
+
+    // Heights are preferred when every input supports them.
+    let psbt = assert_round_trip(&psbt_v2(vec![], vec![time, height]));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_height(10_000).unwrap());
+}
+
+#[test]
+fn test_locktime_determination() {
+    let time = |time: u32| pair(0x11, &time.to_le_bytes());
+    let height = |height: u32| pair(0x12, &height.to_le_bytes());
+    let two_inputs = |fallback: Vec<(u8, Vec<u8>)>, first: Vec<(u8, Vec<u8>)>, second: Vec<(u8, Vec<u8>)>| {
+        encode(vec![
+            [global(2), fallback].concat(),
+            [input(0), first].concat(),
+            [input(1), second].concat(),
+            output(99_999_699),
+            output(100_000_000),
+        ])
+    };
+    let fallback = vec![pair(0x03, &10_000u32.to_le_bytes())];
+
+    let psbt = assert_round_trip(&two_inputs(fallback.clone(), vec![], vec![]));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_height(10_000).unwrap());
+
+    let psbt = assert_round_trip(&two_inputs(
+        fallback.clone(),
+        vec![height(10_000)],
+        vec![height(11_000)],
+    ));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_height(11_000).unwrap());
+
+    let psbt = assert_round_trip(&two_inputs(
+        fallback.clone(),
+        vec![time(1_657_048_459), height(10_000)],
+        vec![time(1_657_048_460)],
+    ));
+    assert_eq!(psbt.unsigned_tx.lock_time, LockTime::from_time(1_657_048_460).unwrap());
+
+    let incompatible = two_inputs(fallback, vec![height(10_000)], vec![time(1_657_048_460)]);
+    assert!(deserialize_psbt(&incompatible).is_err());
+}
+
+#[test]
+fn test_tx_modifiable() {
+    for flags in [0x00, 0x01, 0x02, 0x03, 0x04, 0x07, 0x08, 0xff] {
+        let psbt = assert_round_trip(&psbt_v2(vec![pair(0x06, &[flags])], vec![]));
+        assert_eq!(modifiable_flags(&psbt), Some(flags));
+    }
+}
+
+#[test]
+fn test_all_fields() {
+    assert_round_trip(&psbt_v2(
+        vec![pair(0x03, &0u32.to_le_bytes()), pair(0x06, &[0x07])],
+        vec![
+            pair(0x10, &0xfffffffeu32.to_le_bytes()),
+            pair(0x11, &1_657_048_460u32.to_le_bytes()),
+            pair(0x12, &10_000u32.to_le_bytes()),
+        ],
+    ));
+}
+
+#[test]
+fn test_invalid_v2() {
+    let without = |map: Vec<(u8, Vec<u8>)>, key_type: u8| -> Vec<(u8, Vec<u8>)> {
+        map.into_iter().filter(|(k, _)| *k != key_type).collect()
+    };
+
+    let mut cases = vec![
+        // PSBT_GLOBAL_UNSIGNED_TX is forbidden.
+        psbt_v2(
+            vec![pair(0x00, &bdk_wallet::bitcoin::consensus::serialize(&unsigned_tx()))],
+            vec![],
+        ),
+        // Required locktimes on the wrong side of the threshold.
+        psbt_v2(vec![], vec![pair(0x11, &499_999_999u32.to_le_bytes())]),
+        psbt_v2(vec![], vec![pair(0x12, &500_000_000u32.to_le_bytes())]),
+    ];
+    // Missing required global fields.
+    for key_type in [0x02, 0x04, 0x05] {
+        cases.push(encode(vec![
+            without(global(1), key_type),
+            input(0),
+            output(99_999_699),
+            output(100_000_000),
+        ]));
+    }
+    // Missing required input fields.
+    for key_type in [0x0e, 0x0f] {
+        cases.push(encode(vec![
+            global(1),
+            without(input(0), key_type),
+            output(99_999_699),
+            output(100_000_000),
+        ]));
+    }
+    // Missing required output fields.
+    for key_type in [0x03, 0x04] {
+        cases.push(encode(vec![
+            global(1),
+            input(0),
+            without(output(99_999_699), key_type),
+            output(100_000_000),
+        ]));
+    }
+
+    for case in cases {
+        assert!(deserialize_psbt(&case).is_err());
+    }
+}
+
+#[test]
+fn test_invalid_v0_with_v2_fields() {
+    let key = |type_value: u8| raw::Key {
+        type_value,
+        key: vec![],
+    };
+    let value = 0u32.to_le_bytes().to_vec();
+
+    for type_value in [0x02, 0x03, 0x04, 0x05, 0x06] {
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx()).unwrap();
+        psbt.unknown.insert(key(type_value), value.clone());
+        assert!(deserialize_psbt(&psbt.serialize()).is_err());
+    }
+    for type_value in [0x0e, 0x0f, 0x10, 0x11, 0x12] {
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx()).unwrap();
+        psbt.inputs[0].unknown.insert(key(type_value), value.clone());
+        assert!(deserialize_psbt(&psbt.serialize()).is_err());
+    }
+    for type_value in [0x03, 0x04] {
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx()).unwrap();
+        psbt.outputs[0].unknown.insert(key(type_value), value.clone());
+        assert!(deserialize_psbt(&psbt.serialize()).is_err());
+    }
+}
+
+#[test]
+fn test_set_version() {
+    let mut psbt = Psbt::from_unsigned_tx(unsigned_tx()).unwrap();
+    set_psbt_version(&mut psbt, 2);
+
+    let v2 = assert_round_trip(&serialize_psbt(&psbt));
+    assert_eq!(v2.unsigned_tx, unsigned_tx());
+    assert_eq!(modifiable_flags(&v2), Some(0x03));
+
+    set_psbt_version(&mut psbt, 0);
+    let v0 = deserialize_psbt(&serialize_psbt(&psbt)).expect("valid PSBTv0");
+    assert_eq!(v0.version, 0);
+    assert!(v0.unknown.is_empty());
+    assert_eq!(v0.unsigned_tx, unsigned_tx());
+}
+
+#[test]
+fn test_modifiable_flags_follow_signatures() {
+    let signed = |sighash_type| {
+        let mut psbt = Psbt::from_unsigned_tx(unsigned_tx()).unwrap();
+        set_psbt_version(&mut psbt, 2);
+        sign(&mut psbt, sighash_type);
+        modifiable_flags(&deserialize_psbt(&serialize_psbt(&psbt)).unwrap())
+    };
+
+    assert_eq!(signed(EcdsaSighashType::All), Some(0x00));
+    assert_eq!(signed(EcdsaSighashType::AllPlusAnyoneCanPay), Some(0x01));
+    assert_eq!(signed(EcdsaSighashType::NonePlusAnyoneCanPay), Some(0x03));
+    assert_eq!(signed(EcdsaSighashType::Single), Some(0x04));
+    assert_eq!(signed(EcdsaSighashType::SinglePlusAnyoneCanPay), Some(0x05));
+}
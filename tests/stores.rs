@@ -0,0 +1,86 @@
+//! Test suite for the browser wallet stores.
+
+#![cfg(all(feature = "stores", target_arch = "wasm32"))]
+
+extern crate wasm_bindgen_test;
+
+use bitcoindevkit::{
+    bitcoin::{IndexedDbStore, LocalStorageStore, Wallet},
+    set_panic_hook,
+    types::{KeychainKind, Network},
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const EXTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPe2qpAuh1K1Hig72LCoP4JgNxZM2ZRWHZYnpuw5oHoGBsQm7Qb8mLgPpRJVn3hceWgGQRNbPD6x1pp2Qme2YFRAPeYh7vmvE/84'/1'/0'/0/*)#a6kgzlgq";
+const INTERNAL_DESC: &str = "wpkh(tprv8ZgxMBicQKsPe2qpAuh1K1Hig72LCoP4JgNxZM2ZRWHZYnpuw5oHoGBsQm7Qb8mLgPpRJVn3hceWgGQRNbPD6x1pp2Qme2YFRAPeYh7vmvE/84'/1'/0'/1/*)#vwnfl2cc";
+
+fn new_wallet() -> Wallet {
+    Wallet::create(Network::Signet, EXTERNAL_DESC.into(), INTERNAL_DESC.into()).expect("wallet")
+}
+
+#[wasm_bindgen_test]
+async fn test_local_storage_store() {
+    set_panic_hook();
+
+    let store = LocalStorageStore::new("test_local_storage_store").expect("store");
+    store.clear().expect("clear");
+    assert!(store.initialize().await.expect("initialize").is_none());
+
+    let wallet = new_wallet();
+    store
+        .persist(wallet.take_staged().expect("staged"))
+        .await
+        .expect("persist");
+    wallet.reveal_next_address(KeychainKind::External);
+    store
+        .persist(wallet.take_staged().expect("staged"))
+        .await
+        .expect("persist");
+
+    let changeset = store.initialize().await.expect("initialize").expect("changeset");
+    let loaded = Wallet::load(changeset, None, None, None, None).expect("load");
+    assert_eq!(loaded.derivation_index(KeychainKind::External), Some(0));
+
+    store.clear().expect("clear");
+    assert!(store.initialize().await.expect("initialize").is_none());
+}
+
+#[wasm_bindgen_test]
+async fn test_indexed_db_store() {
+    set_panic_hook();
+
+    let mut store = IndexedDbStore::open("test_indexed_db_store".into(), Some(3))
+        .await
+        .expect("open");
+    store.clear().await.expect("clear");
+    assert!(store.initialize().await.expect("initialize").is_none());
+
+    let wallet = new_wallet();
+    store
+        .persist(wallet.take_staged().expect("staged"))
+        .await
+        .expect("persist");
+    for _ in 0..5 {
+        wallet.reveal_next_address(KeychainKind::External);
+        store
+            .persist(wallet.take_staged().expect("staged"))
+            .await
+            .expect("persist");
+    }
+
+    // Records are compacted on the way, reopening the store must see the same state.
+    let mut store = IndexedDbStore::open("test_indexed_db_store".into(), Some(3))
+        .await
+        .expect("open");
+    let changeset = store.initialize().await.expect("initialize").expect("changeset");
+    let loaded = Wallet::load(changeset, None, None, None, None).expect("load");
+    assert_eq!(
+        loaded.derivation_index(KeychainKind::External),
+        wallet.derivation_index(KeychainKind::External)
+    );
+
+    store.clear().await.expect("clear");
+    assert!(store.initialize().await.expect("initialize").is_none());
+}